@@ -1,17 +1,196 @@
-use cursive::align::HAlign;
-use cursive::align::*;
-use cursive::direction::*;
+use colored::*;
 use cursive::event::{Event, Key};
-use cursive::theme::{Color, Effect};
+use cursive::theme::{BaseColor, Color, Effect};
 use cursive::utils::markup::StyledString;
 use cursive::view::*;
 use cursive::views::*;
 use cursive::Cursive;
+use iron::Listening;
 use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::mem;
+use std::path::Path;
 
+use super::infrastructure::{open_in_editor, Error};
 use super::model::*;
 
-use std::boxed::Box;
+/// Port of the server started in the TUI.
+const SERVER_PORT: u64 = 8765;
+
+/// Lines of the markdown in the preview.
+const PREVIEW_LINES: usize = 8;
+
+const KEYS: &str = "/ search  o sort  Space check  Enter edit  p publish  t title/tags  ? keys  q quit";
+
+const HELP: &str = "/        Search the titles and the tags, Enter or Esc to return
+o / O    Sort by the date, the title or the target / Reverse the order
+h / l    Move to the tags / the contents, select a tag to filter
+Space    Check or uncheck the content
+a        Check all the shown contents, or uncheck them
+Enter/e  Open the content with $VISUAL or $EDITOR
+p        Publish the drafts and turn the posts into drafts
+t        Edit the title and the tags
+d/Del    Delete the contents
+b        Build the site
+s        Build the site and serve it on http://127.0.0.1:8765
+r        Reload the contents
+q        Quit
+
+p and d act on the checked contents, or the selected one if none is checked.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Sort {
+    Date,
+    Title,
+    Target,
+}
+
+impl Default for Sort {
+    fn default() -> Sort {
+        return Sort::Date;
+    }
+}
+
+impl Sort {
+    fn next(self) -> Sort {
+        return match self {
+            Sort::Date => Sort::Title,
+            Sort::Title => Sort::Target,
+            Sort::Target => Sort::Date,
+        };
+    }
+
+    fn name(self) -> &'static str {
+        return match self {
+            Sort::Date => "date",
+            Sort::Title => "title",
+            Sort::Target => "target",
+        };
+    }
+
+    /// The latest first by the date, the drafts first by the target.
+    fn compare(self, a: &Content, b: &Content) -> Ordering {
+        let by_date = b.create_time.cmp(&a.create_time);
+        return match self {
+            Sort::Date => by_date,
+            Sort::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()).then(by_date),
+            Sort::Target => a.target.cmp(&b.target).then(by_date),
+        };
+    }
+}
+
+/// Something run outside of the TUI, the TUI is shown again when it is done.
+enum Action {
+    Edit(String),
+    Build,
+    Serve,
+}
+
+/// What is kept while the TUI is closed to run an action.
+#[derive(Default)]
+struct Session {
+    /// Paths of the checked contents.
+    checked: BTreeSet<String>,
+    /// Path of the selected content.
+    selected: Option<String>,
+    /// The server is never stopped, as it can not be.
+    server: Option<Listening>,
+    message: String,
+    /// Only the contents whose title or tags contain the text, case insensitive.
+    search: String,
+    /// Only the contents having the tag.
+    tag: Option<String>,
+    sort: Sort,
+    reverse: bool,
+}
+
+struct State {
+    site: Site,
+    /// All contents, the drafts included, the latest first.
+    contents: Vec<Content>,
+    session: Session,
+    action: Option<Action>,
+}
+
+impl State {
+    fn load(source: &SiteSource) -> Result<State, Error> {
+        let site = source.load()?;
+        let mut state = State {
+            site: site,
+            contents: vec![],
+            session: Session::default(),
+            action: None,
+        };
+        state.reload()?;
+        return Ok(state);
+    }
+
+    /// Load the contents again, the checked contents which no longer exist are removed.
+    fn reload(&mut self) -> Result<(), Error> {
+        let mut contents = vec![];
+        let mut failed = 0;
+        for (_, result) in Content::load_every(&self.site)? {
+            match result {
+                Ok(content) => contents.push(content),
+                Err(_) => failed += 1,
+            }
+        }
+        contents.sort_by(|a, b| b.create_time.cmp(&a.create_time));
+        self.session
+            .checked
+            .retain(|path| contents.iter().any(|x| &x.path == path));
+        if failed > 0 {
+            self.session.message = format!(
+                "{} content(s) failed to load, run `ims check` to see why.",
+                failed
+            );
+        }
+        self.contents = contents;
+        return Ok(());
+    }
+
+    /// The contents passing the search and the tag, in the order of the sort.
+    fn visible(&self) -> Vec<&Content> {
+        let search = self.session.search.trim().to_lowercase();
+        let mut contents: Vec<&Content> = self
+            .contents
+            .iter()
+            .filter(|x| self.session.tag.as_ref().map_or(true, |tag| x.tags.contains(tag)))
+            .filter(|x| {
+                search.is_empty()
+                    || x.title.to_lowercase().contains(&search)
+                    || x.tags.iter().any(|tag| tag.to_lowercase().contains(&search))
+            })
+            .collect();
+        contents.sort_by(|a, b| self.session.sort.compare(a, b));
+        if self.session.reverse {
+            contents.reverse();
+        }
+        return contents;
+    }
+
+    /// The tags of all contents, with the number of the contents having each.
+    fn tags(&self) -> BTreeMap<&str, usize> {
+        let mut tags = BTreeMap::new();
+        for tag in self.contents.iter().flat_map(|x| x.tags.iter()) {
+            *tags.entry(tag.as_str()).or_insert(0) += 1;
+        }
+        return tags;
+    }
+
+    fn get(&self, path: &str) -> Option<&Content> {
+        return self.contents.iter().find(|x| x.path == path);
+    }
+
+    /// The checked contents, or the selected one if none is checked.
+    fn targets(&self) -> Vec<String> {
+        if !self.session.checked.is_empty() {
+            return self.session.checked.iter().cloned().collect();
+        }
+        return self.session.selected.iter().cloned().collect();
+    }
+}
 
 fn config_global_key(siv: &mut Cursive) {
     siv.add_global_callback('q', |s| s.quit());
@@ -22,65 +201,411 @@ fn config_global_key(siv: &mut Cursive) {
     siv.add_global_callback('k', |s| {
         s.on_event(Event::from(Key::Up));
     });
+    siv.add_global_callback('h', |s| {
+        s.on_event(Event::from(Key::Left));
+    });
+    siv.add_global_callback('l', |s| {
+        s.on_event(Event::from(Key::Right));
+    });
+    siv.add_global_callback('/', |s| {
+        let _ = s.focus_id("search");
+    });
+    siv.add_global_callback('?', |s| {
+        s.add_layer(Dialog::info(HELP).title("Keys"));
+    });
 }
 
-fn create_view() -> ViewBox {
-    let site = Site::load("/home/baiyan/Projects/Own/baiyan").unwrap();
-    let contents = Content::load_all(&site).unwrap();
-    let mut contents_views = ListView::new().delimiter();
-    contents_views.add_child(
-        "",
-        LinearLayout::horizontal()
-            .child(PaddedView::new(
-                (1, 1, 0, 0),
-                BoxView::with_fixed_width(5, TextView::new("[+]").effect(Effect::Bold)),
-            ))
-            .child(PaddedView::new(
-                (1, 1, 0, 0),
-                BoxView::with_fixed_width(5, TextView::new("ID").effect(Effect::Bold)),
-            ))
-            .child(PaddedView::new(
-                (1, 1, 0, 0),
-                BoxView::with_fixed_width(30, TextView::new("TITLE").effect(Effect::Bold)),
-            ))
-            .child(PaddedView::new(
-                (1, 1, 0, 0),
-                BoxView::with_fixed_width(30, TextView::new("TYPE").effect(Effect::Bold)),
-            ))
-            .child(PaddedView::new(
-                (1, 1, 0, 0),
-                BoxView::with_fixed_width(30, TextView::new("DATETIME").effect(Effect::Bold)),
-            )),
-    );
-    for content in contents {
-        contents_views.add_child(
-            "",
-            LinearLayout::horizontal()
-                .child(PaddedView::new(
-                    (1, 1, 0, 0),
-                    BoxView::with_fixed_width(5, Checkbox::new()),
-                ))
-                .child(PaddedView::new(
-                    (1, 1, 0, 0),
-                    BoxView::with_fixed_width(5, TextView::new(format!("{}", content.id))),
-                ))
-                .child(PaddedView::new(
-                    (1, 1, 0, 0),
-                    BoxView::with_fixed_width(30, TextView::new(content.title)),
-                ))
-                .child(PaddedView::new(
-                    (1, 1, 0, 0),
-                    BoxView::with_fixed_width(30, TextView::new(content.target)),
-                ))
-                .child(PaddedView::new(
-                    (1, 1, 0, 0),
-                    BoxView::with_fixed_width(
-                        30,
-                        TextView::new(content.create_time.format("%b %e, %Y").to_string()),
-                    ),
-                )),
+/// Cut or pad the text to the width of the terminal cells.
+fn fit(text: &str, width: usize) -> String {
+    let mut result = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let size = StyledString::plain(c.to_string()).width();
+        if used + size > width {
+            break;
+        }
+        result.push(c);
+        used += size;
+    }
+    result.push_str(&" ".repeat(width - used));
+    return result;
+}
+
+fn create_label(content: &Content, checked: bool) -> StyledString {
+    let mut label = StyledString::plain(if checked { "[x] " } else { "[ ] " });
+    label.append_plain(fit(&content.title, 32));
+    label.append_plain(" ");
+    if content.target == "POST" {
+        label.append_plain(fit(&content.target, 8));
+    } else {
+        label.append_styled(fit(&content.target, 8), Color::Dark(BaseColor::Yellow));
+    }
+    label.append_plain(" ");
+    label.append_plain(fit(&content.create_time.format("%b %e, %Y").to_string(), 14));
+    label.append_plain(content.tags.join(", "));
+    return label;
+}
+
+/// Show the contents, the tags and the message of the state.
+fn refresh(siv: &mut Cursive) {
+    let data = siv.with_user_data(|state: &mut State| {
+        let visible = state.visible();
+        let rows: Vec<(StyledString, String)> = visible
+            .iter()
+            .map(|x| (create_label(x, state.session.checked.contains(&x.path)), x.path.clone()))
+            .collect();
+        // The first content is selected if the selected one is no longer shown.
+        let selected = state
+            .session
+            .selected
+            .as_ref()
+            .and_then(|path| rows.iter().position(|x| &x.1 == path))
+            .unwrap_or(0);
+        let tags = state.tags();
+        let mut tag_rows = vec![(format!("{} {}", fit("All", 16), state.contents.len()), String::new())];
+        for (tag, count) in &tags {
+            tag_rows.push((format!("{} {}", fit(tag, 16), count), tag.to_string()));
+        }
+        let tag = state.session.tag.as_ref().map_or("", |x| x.as_str());
+        let tag_selected = tag_rows.iter().position(|x| x.1 == tag).unwrap_or(0);
+        let title = format!(
+            "Contents ({}/{}, {} checked, by {}{})",
+            rows.len(),
+            state.contents.len(),
+            state.session.checked.len(),
+            state.session.sort.name(),
+            if state.session.reverse { ", reversed" } else { "" }
         );
+        let message = state.session.message.clone();
+        let selected_path = rows.get(selected).map(|x| x.1.clone());
+        state.session.selected = selected_path;
+        (rows, selected, tag_rows, tag_selected, title, message)
+    });
+    let (rows, selected, tag_rows, tag_selected, title, message) = match data {
+        Some(data) => data,
+        None => return,
+    };
+    siv.call_on_id("contents", |view: &mut SelectView<String>| {
+        view.clear();
+        for (label, path) in rows {
+            view.add_item(label, path);
+        }
+        view.set_selection(selected);
+    });
+    siv.call_on_id("tags", |view: &mut SelectView<String>| {
+        view.clear();
+        for (label, tag) in tag_rows {
+            view.add_item(label, tag);
+        }
+        view.set_selection(tag_selected);
+    });
+    siv.call_on_id("contents_panel", |view: &mut Panel<LinearLayout>| view.set_title(title));
+    siv.call_on_id("message", |view: &mut TextView| view.set_content(message));
+    show_preview(siv);
+}
+
+/// Show the mark info and the beginning of the selected content.
+fn show_preview(siv: &mut Cursive) {
+    let preview = siv
+        .with_user_data(|state: &mut State| match state.session.selected {
+            Some(ref path) => Content::preview(&state.site, path, PREVIEW_LINES)
+                .unwrap_or_else(|error| format!("{}", error)),
+            None => String::new(),
+        })
+        .unwrap_or_default();
+    siv.call_on_id("preview", |view: &mut TextView| view.set_content(preview));
+}
+
+fn search(siv: &mut Cursive, text: &str) {
+    let text = text.to_string();
+    siv.with_user_data(|state: &mut State| state.session.search = text);
+    refresh(siv);
+}
+
+fn clear_search(siv: &mut Cursive) {
+    siv.call_on_id("search", |view: &mut EditView| view.set_content(""));
+    search(siv, "");
+    let _ = siv.focus_id("contents");
+}
+
+fn select_tag(siv: &mut Cursive, tag: &String) {
+    let tag = if tag.is_empty() { None } else { Some(tag.clone()) };
+    siv.with_user_data(|state: &mut State| state.session.tag = tag);
+    refresh(siv);
+}
+
+fn change_sort(siv: &mut Cursive, reverse: bool) {
+    siv.with_user_data(|state: &mut State| {
+        if reverse {
+            state.session.reverse = !state.session.reverse;
+        } else {
+            state.session.sort = state.session.sort.next();
+            state.session.reverse = false;
+        }
+    });
+    refresh(siv);
+}
+
+/// Run the change on the state, then show the error or the new contents.
+fn update<F>(siv: &mut Cursive, change: F)
+where
+    F: FnOnce(&mut State) -> Result<(), Error>,
+{
+    let result = siv
+        .with_user_data(|state: &mut State| {
+            state.session.message.clear();
+            let result = change(state);
+            // Show the contents changed before the error.
+            let reloaded = state.reload();
+            result.and(reloaded)
+        })
+        .unwrap_or(Ok(()));
+    if let Err(error) = result {
+        siv.add_layer(Dialog::info(format!("{}", error)).title("Error"));
+    }
+    refresh(siv);
+}
+
+fn toggle_check(siv: &mut Cursive) {
+    siv.with_user_data(|state: &mut State| {
+        if let Some(path) = state.session.selected.clone() {
+            if !state.session.checked.remove(&path) {
+                state.session.checked.insert(path);
+            }
+        }
+    });
+    refresh(siv);
+}
+
+fn toggle_check_all(siv: &mut Cursive) {
+    siv.with_user_data(|state: &mut State| {
+        let visible: Vec<String> = state.visible().iter().map(|x| x.path.clone()).collect();
+        if visible.iter().all(|x| state.session.checked.contains(x)) {
+            for path in &visible {
+                state.session.checked.remove(path);
+            }
+        } else {
+            state.session.checked.extend(visible);
+        }
+    });
+    refresh(siv);
+}
+
+fn toggle_published(siv: &mut Cursive) {
+    update(siv, |state| {
+        let targets = state.targets();
+        for path in &targets {
+            let published = state.get(path).map_or(false, |x| x.target != "POST");
+            Content::set_published(&state.site, path, published)?;
+        }
+        state.session.message = format!("Changed the target of {} content(s).", targets.len());
+        return Ok(());
+    });
+}
+
+fn show_edit_dialog(siv: &mut Cursive) {
+    let content = siv.with_user_data(|state: &mut State| {
+        state
+            .session
+            .selected
+            .as_ref()
+            .and_then(|path| state.get(path))
+            .map(|x| (x.path.clone(), x.title.clone(), x.tags.join(", ")))
+    });
+    let (path, title, tags) = match content {
+        Some(Some(content)) => content,
+        _ => return,
+    };
+    let (submit_title, submit_tags, save) = (path.clone(), path.clone(), path.clone());
+    let form = ListView::new()
+        .child(
+            "Title",
+            EditView::new()
+                .content(title)
+                .on_submit(move |s, _| save_edit_dialog(s, &submit_title))
+                .with_id("title")
+                .fixed_width(50),
+        )
+        .child(
+            "Tags",
+            EditView::new()
+                .content(tags)
+                .on_submit(move |s, _| save_edit_dialog(s, &submit_tags))
+                .with_id("tags")
+                .fixed_width(50),
+        );
+    let dialog = Dialog::around(form)
+        .title(path)
+        .button("Save", move |s| save_edit_dialog(s, &save))
+        .button("Cancel", |s| {
+            s.pop_layer();
+        });
+    siv.add_layer(dialog);
+}
+
+/// Save the title and the tags of the dialog, the tags are separated by commas.
+fn save_edit_dialog(siv: &mut Cursive, path: &str) {
+    let title = siv.call_on_id("title", |view: &mut EditView| view.get_content());
+    let tags = siv.call_on_id("tags", |view: &mut EditView| view.get_content());
+    siv.pop_layer();
+    let (title, tags) = match (title, tags) {
+        (Some(title), Some(tags)) => (title.to_string(), tags.to_string()),
+        _ => return,
+    };
+    let tags: Vec<String> = tags
+        .split(',')
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())
+        .collect();
+    let path = path.to_string();
+    update(siv, move |state| {
+        let values = [("title", json!(title)), ("tags", json!(tags))];
+        Content::update_mark(&state.site, &path, &values)?;
+        state.session.message = format!("Saved {}.", path);
+        return Ok(());
+    });
+}
+
+fn show_delete_dialog(siv: &mut Cursive) {
+    let targets = siv
+        .with_user_data(|state: &mut State| state.targets())
+        .unwrap_or(vec![]);
+    if targets.is_empty() {
+        return;
+    }
+    let message = format!("Delete {} content(s)?\n\n{}", targets.len(), targets.join("\n"));
+    let dialog = Dialog::text(message)
+        .title("Delete")
+        .button("Delete", move |s| {
+            s.pop_layer();
+            let targets = targets.clone();
+            update(s, move |state| {
+                for path in &targets {
+                    Content::delete(&state.site, path)?;
+                }
+                state.session.message = format!("Deleted {} content(s).", targets.len());
+                return Ok(());
+            });
+        })
+        .button("Cancel", |s| {
+            s.pop_layer();
+        });
+    siv.add_layer(dialog);
+}
+
+/// Close the TUI to run the action.
+fn run_action(siv: &mut Cursive, action: Action) {
+    siv.with_user_data(|state: &mut State| state.action = Some(action));
+    siv.quit();
+}
+
+fn edit_selected(siv: &mut Cursive) {
+    let path = siv.with_user_data(|state: &mut State| state.session.selected.clone());
+    if let Some(Some(path)) = path {
+        run_action(siv, Action::Edit(path));
     }
+}
+
+fn serve(siv: &mut Cursive) {
+    let running = siv
+        .with_user_data(|state: &mut State| state.session.server.is_some())
+        .unwrap_or(false);
+    if running {
+        let message = format!("The server is running on http://127.0.0.1:{}.", SERVER_PORT);
+        siv.add_layer(Dialog::info(message).title("Serve"));
+        return;
+    }
+    run_action(siv, Action::Serve);
+}
+
+/// A dialog telling why the site can not be shown, the app quits when it is closed.
+fn create_error_view(source: &SiteSource, error: &Error) -> ViewBox {
+    let path = Path::new(&source.root)
+        .canonicalize()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or(source.root.clone());
+    let message = if let (Some(config), false) = (source.config.as_ref(), source.config_path().is_file()) {
+        format!("The config file {} is not exists.", config)
+    } else if !source.config_path().is_file() {
+        format!(
+            "No site.json in {}.\n\nRun `ims init` to create a site in this directory, \
+             or run `ims tui <PATH>` to open another site.",
+            path
+        )
+    } else {
+        format!("Failed to load the site in {}.\n\n{}", path, error)
+    };
+    let dialog = Dialog::around(PaddedView::new((1, 1, 1, 0), TextView::new(message)))
+        .title("Ims")
+        .button("Quit", |s| s.quit());
+    return ViewBox::boxed(BoxView::with_max_width(72, dialog));
+}
+
+fn create_view(site: &Site) -> ViewBox {
+    let header = StyledString::styled(
+        format!("    {}{}{}TAGS", fit("TITLE", 33), fit("TARGET", 9), fit("DATETIME", 14)),
+        Effect::Bold,
+    );
+    let list = SelectView::<String>::new()
+        .on_select(|s, path: &String| {
+            let path = path.clone();
+            s.with_user_data(|state: &mut State| state.session.selected = Some(path));
+            show_preview(s);
+        })
+        .on_submit(|s, _: &String| edit_selected(s))
+        .with_id("contents");
+    let list = OnEventView::new(list)
+        .on_event(' ', toggle_check)
+        .on_event('a', toggle_check_all)
+        .on_event('e', edit_selected)
+        .on_event('p', toggle_published)
+        .on_event('t', show_edit_dialog)
+        .on_event('d', show_delete_dialog)
+        .on_event(Key::Del, show_delete_dialog)
+        .on_event('b', |s| run_action(s, Action::Build))
+        .on_event('s', serve)
+        .on_event('r', |s| update(s, |_| Ok(())))
+        .on_event('o', |s| change_sort(s, false))
+        .on_event('O', |s| change_sort(s, true));
+    let contents = Panel::new(
+        LinearLayout::vertical()
+            .child(TextView::new(header))
+            .child(list.scrollable().full_height()),
+    )
+    .with_id("contents_panel");
+    let search_bar = LinearLayout::horizontal()
+        .child(TextView::new("Search: "))
+        .child(
+            OnEventView::new(
+                EditView::new()
+                    .on_edit(|s, text, _| search(s, text))
+                    .on_submit(|s, _| {
+                        let _ = s.focus_id("contents");
+                    })
+                    .with_id("search"),
+            )
+            .on_event(Key::Esc, clear_search)
+            .full_width(),
+        );
+    let tags = Panel::new(
+        SelectView::<String>::new()
+            .on_select(select_tag)
+            .with_id("tags")
+            .scrollable()
+            .full_height(),
+    )
+    .title("Tags")
+    .fixed_width(26);
+    let preview = Panel::new(TextView::new("").with_id("preview").scrollable())
+        .title("Preview")
+        .fixed_height(10);
+    let main = LinearLayout::horizontal().child(tags).child(
+        LinearLayout::vertical()
+            .child(contents)
+            .child(PaddedView::new((1, 1, 0, 0), search_bar))
+            .child(preview)
+            .full_width(),
+    );
     let container = LinearLayout::vertical()
         .child(
             Panel::new(
@@ -88,7 +613,7 @@ fn create_view() -> ViewBox {
                     .child(PaddedView::new(
                         (1, 2, 0, 0),
                         TextView::new(StyledString::styled(
-                            site.author.as_ref(),
+                            site.author.as_str(),
                             Color::Rgb(200, 0, 0),
                         )),
                     ))
@@ -97,16 +622,90 @@ fn create_view() -> ViewBox {
                         TextView::new(format!("<{}>", site.subtitle)),
                     )),
             )
-            .title(site.title.as_ref()),
+            .title(site.title.as_str()),
         )
-        .child(Panel::new(contents_views));
+        .child(main)
+        .child(PaddedView::new((1, 1, 0, 0), TextView::new("").with_id("message")))
+        .child(PaddedView::new((1, 1, 0, 0), TextView::new(KEYS)));
     return ViewBox::boxed(container);
 }
 
-pub fn run() {
-    let view = create_view();
-    let mut siv = Cursive::default();
-    config_global_key(&mut siv);
-    siv.add_layer(view);
-    siv.run();
+fn wait_for_enter() {
+    println!("\nPress Enter to return to ims.");
+    let mut line = String::new();
+    let _ = io::stdin().read_line(&mut line);
+}
+
+/// Run the action in the terminal, with the output of the commands.
+fn perform(source: &SiteSource, session: &mut Session, action: Action) {
+    let result = match action {
+        Action::Edit(path) => source.load()
+            .and_then(|site| site.get_content_path())
+            .and_then(|content_path| open_in_editor(&Path::new(&content_path).join(&path)))
+            .map(|_| session.message = format!("Edited {}.", path)),
+        Action::Build => source.load()
+            .and_then(|site| site.build(false, false))
+            .map(|report| {
+                report.print();
+                session.message = "Built the site.".to_string();
+                wait_for_enter();
+            }),
+        Action::Serve => source.load().and_then(|site| {
+            site.build(false, false)?.print();
+            session.server = Some(site.start_server(SERVER_PORT)?);
+            session.message = format!("Serving on http://127.0.0.1:{}.", SERVER_PORT);
+            println!("{0:>12} http://127.0.0.1:{1}", "Running".green().bold(), SERVER_PORT);
+            wait_for_enter();
+            return Ok(());
+        }),
+    };
+    if let Err(error) = result {
+        println!("{0:>12} {1}", "Error".red().bold(), error);
+        session.message = format!("{}", error);
+        wait_for_enter();
+    }
+}
+
+/// Run the TUI of the site in the directory.
+pub fn run(source: &SiteSource) {
+    let mut session = Session::default();
+    loop {
+        let mut siv = Cursive::default();
+        config_global_key(&mut siv);
+        match State::load(source) {
+            Ok(mut state) => {
+                let message = mem::replace(&mut state.session.message, String::new());
+                state.session = mem::replace(&mut session, Session::default());
+                if !message.is_empty() {
+                    state.session.message = message;
+                }
+                let search = state.session.search.clone();
+                siv.add_layer(create_view(&state.site));
+                siv.call_on_id("search", |view: &mut EditView| view.set_content(search));
+                siv.set_user_data(state);
+                refresh(&mut siv);
+                let _ = siv.focus_id("contents");
+            }
+            Err(error) => {
+                siv.add_layer(create_error_view(source, &error));
+                siv.run();
+                break;
+            }
+        }
+        siv.run();
+        let action = siv.with_user_data(|state: &mut State| {
+            session = mem::replace(&mut state.session, Session::default());
+            state.action.take()
+        });
+        // The terminal is restored when the TUI is dropped.
+        drop(siv);
+        match action {
+            Some(Some(action)) => perform(source, &mut session, action),
+            _ => break,
+        }
+    }
+    // Dropping the server waits for it forever.
+    if let Some(server) = session.server.take() {
+        mem::forget(server);
+    }
 }
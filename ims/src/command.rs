@@ -1,4 +1,10 @@
 use std;
+use std::fs;
+use std::io::Write;
+
+use colored::*;
+use rayon;
+use serde_json;
 
 use super::model::*;
 use super::infrastructure::*;
@@ -10,8 +16,8 @@ type Result<T> = std::result::Result<T, Error>;
 
 pub mod site_command {
     use super::*;
-    pub fn init() -> Result<()> {
-        new(".")?;
+    pub fn init(source: &SiteSource) -> Result<()> {
+        new(&source.root)?;
         return Ok(());
     }
 
@@ -20,26 +26,94 @@ pub mod site_command {
         return Ok(());
     }
 
-    pub fn info(root_path: &str) -> Result<()> {
-        let site = Site::load(root_path)?;
+    pub fn info(source: &SiteSource) -> Result<()> {
+        let site = source.load()?;
         site.info()?;
         return Ok(());
     }
 
-    pub fn build(root_path: &str) -> Result<()> {
-        let site = Site::load(root_path)?;
-        site.build()?;
+    pub fn build(
+        source: &SiteSource,
+        minify: bool,
+        clean: bool,
+        strict: bool,
+        jobs: usize,
+        report_format: &str,
+        report_file: Option<&str>,
+    ) -> Result<()> {
+        if jobs > 0 {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(jobs)
+                .build_global()
+                .map_err(|err| {
+                    Error::new("Failed to create the thread pool.").with_source(err)
+                })?;
+        }
+        let mut site = source.load()?;
+        site.minify = site.minify || minify;
+        let report = site.build(clean, strict)?;
+        match report_format {
+            "json" => {
+                let data = serde_json::to_string_pretty(&report).map_err(|err| {
+                    Error::new("Failed to convert the build report.").with_source(err)
+                })?;
+                if let Some(report_file) = report_file {
+                    let mut file = fs::File::create(report_file).map_err(|err| {
+                        Error::new("Failed to create the report file.").with_source(err)
+                    })?;
+                    file.write_all(&data.into_bytes()).map_err(|err| {
+                        Error::new("Failed to write the report file.").with_source(err)
+                    })?;
+                } else {
+                    println!("{}", data);
+                }
+            }
+            _ => report.print(),
+        }
+        return Ok(());
+    }
+
+    /// Check the mark info of all contents.
+    pub fn check(source: &SiteSource) -> Result<()> {
+        let site = source.load()?;
+        let diagnostics = Content::check_all(&site)?;
+        for diagnostic in &diagnostics {
+            diagnostic.print();
+        }
+        let errors = diagnostics.iter().filter(|x| x.is_error()).count();
+        if errors > 0 {
+            return Err(Error::new(&format!("Found {} error(s) in the contents.", errors))
+                .with_kind(ErrorKind::FrontMatter));
+        }
+        return Ok(());
+    }
+
+    /// Check the links in the generated html files.
+    pub fn check_links(source: &SiteSource, allowlist: Option<&str>) -> Result<()> {
+        let site = source.load()?;
+        let allowlist = match allowlist {
+            Some(path) => Some(LinkChecker::load_allowlist(path)?),
+            None => None,
+        };
+        let diagnostics = LinkChecker::new(&site, allowlist)?.check()?;
+        for diagnostic in &diagnostics {
+            diagnostic.print();
+        }
+        if !diagnostics.is_empty() {
+            return Err(Error::new(&format!("Found {} broken link(s).", diagnostics.len()))
+                .with_kind(ErrorKind::Build));
+        }
         return Ok(());
     }
 
-    pub fn publish(root_path: &str) -> Result<()> {
-        let site = Site::load(root_path)?;
+    pub fn publish(source: &SiteSource) -> Result<()> {
+        let site = source.load()?;
 
         site.publish()?;
         return Ok(());
     }
-    pub fn server(root_path: &str, port: u64) -> Result<()> {
-        let site = Site::load(root_path)?;
+    pub fn server(source: &SiteSource, port: u64) -> Result<()> {
+        let site = source.load()?;
         site.server(port)?;
         return Ok(());
     }
@@ -47,15 +121,82 @@ pub mod site_command {
 
 pub mod content_command {
     use super::*;
-    pub fn new(root_path: &str, path: &str) -> Result<()> {
-        let site = Site::load(root_path)?;
-        Content::new(&site, path)?;
+    /// Create a new content, and open it with the editor if `edit`.
+    pub fn new(source: &SiteSource, path: &str, options: &NewOptions, edit: bool) -> Result<()> {
+        let site = source.load()?;
+        Content::new(&site, path, options)?;
+        if edit {
+            open_in_editor(&std::path::Path::new(&site.get_content_path()?).join(path))?;
+        }
+        return Ok(());
+    }
+
+    pub fn list(source: &SiteSource, options: &ListOptions) -> Result<()> {
+        let site = source.load()?;
+        Content::list(&site, options)?;
+        return Ok(());
+    }
+
+    pub fn publish(source: &SiteSource, target: &str) -> Result<()> {
+        let site = source.load()?;
+        Content::publish(&site, target)?;
         return Ok(());
     }
 
-    pub fn list(root_path: &str) -> Result<()> {
-        let site = Site::load(root_path)?;
-        Content::list(&site)?;
+    pub fn unpublish(source: &SiteSource, target: &str) -> Result<()> {
+        let site = source.load()?;
+        Content::unpublish(&site, target)?;
+        return Ok(());
+    }
+
+    pub fn rename(source: &SiteSource, target: &str, new_path: &str) -> Result<()> {
+        let site = source.load()?;
+        Content::rename(&site, target, new_path)?;
+        return Ok(());
+    }
+
+    pub fn remove(source: &SiteSource, target: &str, yes: bool) -> Result<()> {
+        let site = source.load()?;
+        Content::remove(&site, target, yes)?;
+        return Ok(());
+    }
+
+    /// Import the posts of a Hexo, Jekyll or Hugo site, or a WordPress export.
+    pub fn import(
+        source: &SiteSource,
+        format: &str,
+        source_path: &str,
+        attachments: Option<&str>,
+        force: bool,
+    ) -> Result<()> {
+        let site = source.load()?;
+        let mut importer = Importer::new(&site, force);
+        let source_path = std::path::Path::new(source_path);
+        if format == "wordpress" {
+            importer.import_wordpress(source_path, attachments.map(std::path::Path::new))?;
+        } else {
+            let generator = Generator::from_name(format)
+                .ok_or(Error::new(&format!("Unknown format \"{}\".", format)))?;
+            importer.import_site(generator, source_path)?;
+        }
+        return importer.finish();
+    }
+
+    /// Write the contents in the format of another generator, or the render model as json.
+    pub fn export(source: &SiteSource, format: &str, directory: &str, force: bool) -> Result<()> {
+        let site = source.load()?;
+        let format = ExportFormat::from_name(format)
+            .ok_or(Error::new(&format!("Unknown format \"{}\".", format)))?;
+        let mut exporter = Exporter::new(&site, std::path::Path::new(directory), force)?;
+        exporter.export(format)?;
+        return exporter.finish();
+    }
+
+    /// Give a new id to the contents whose id is copied from another content.
+    pub fn fix_ids(source: &SiteSource) -> Result<()> {
+        let site = source.load()?;
+        let count = Content::fix_ids(&site)?;
+        println!("{0:>12} {1} content(s)", "Fixed".green().bold(), count);
         return Ok(());
     }
 }
@@ -1,14 +1,69 @@
-use clap::{App, Arg, SubCommand};
+use atty;
+use clap::{App, Arg, ArgMatches, SubCommand};
 use colored::*;
 use command::{content_command, site_command};
+use log::LevelFilter;
+use simplelog::*;
 use super::app;
+use super::infrastructure::{Error, ErrorKind};
+use super::model::{Content, ListOptions, NewOptions, Overwrite, SiteSource, LIST_COLUMNS};
+use std::cmp;
+use std::fs::File;
+use std::io;
+use std::process;
 
 pub fn run() {
     let app = App::new("Ims")
         .version("1.0")
         .author("S W. <imseean@gmail.com>")
         .about("A Simple Static Site Generator.")
-        .version_short("v")
+        .version_short("V")
+        .arg(
+            Arg::with_name("source")
+                .help("The root of the site. Default use the current directory.")
+                .short("s")
+                .long("source")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("config")
+                .help("The config file of the site. Default use site.json in the root.")
+                .long("config")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .help("Log the debug messages, -vv for the trace messages.")
+                .short("v")
+                .long("verbose")
+                .multiple(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .help("Log only the warnings, -qq for only the errors.")
+                .short("q")
+                .long("quiet")
+                .multiple(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("log-file")
+                .help("Also write the log to the file, at the debug level at least.")
+                .long("log-file")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("color")
+                .help("When to color the output. Default auto, only for a terminal.")
+                .long("color")
+                .possible_values(&["auto", "always", "never"])
+                .takes_value(true)
+                .global(true),
+        )
         .subcommand(
             SubCommand::with_name("new")
                 .about("Create a new site.")
@@ -32,6 +87,42 @@ pub fn run() {
         .subcommand(
             SubCommand::with_name("build")
                 .about("Build the current site.")
+                .arg(
+                    Arg::with_name("minify")
+                        .help("Minify the generated html/css/js files.")
+                        .long("minify"),
+                )
+                .arg(
+                    Arg::with_name("clean")
+                        .help("Clear the build directory and render all files again.")
+                        .long("clean"),
+                )
+                .arg(
+                    Arg::with_name("strict")
+                        .help("Fail without writing any file if a content or template is broken.")
+                        .long("strict"),
+                )
+                .arg(
+                    Arg::with_name("jobs")
+                        .help("Number of the parallel jobs. Default use the number of CPUs.")
+                        .short("j")
+                        .long("jobs")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("report")
+                        .help("Format of the build report.")
+                        .long("report")
+                        .possible_values(&["text", "json"])
+                        .default_value("text")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("report-file")
+                        .help("Write the build report to the file instead of the stdout.")
+                        .long("report-file")
+                        .takes_value(true),
+                )
                 .display_order(3),
         )
         .subcommand(
@@ -39,6 +130,22 @@ pub fn run() {
                 .about("Publish the current site.")
                 .display_order(4),
         )
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Check the mark info of all contents.")
+                .subcommand(
+                    SubCommand::with_name("links")
+                        .about("Check the links in the generated html files.")
+                        .arg(
+                            Arg::with_name("allowlist")
+                                .long("allowlist")
+                                .takes_value(true)
+                                .value_name("FILE")
+                                .help("Verify the external links against the prefixes in the file, one per line."),
+                        ),
+                )
+                .display_order(7),
+        )
         .subcommand(
             SubCommand::with_name("server")
                 .about("Lanuch server.")
@@ -67,58 +174,373 @@ pub fn run() {
                                 .short("d")
                                 .long("draft"),
                         )
+                        .arg(
+                            Arg::with_name("kind")
+                                .help("Kind of the content, archetypes/<KIND>.md is used if it exists.")
+                                .short("k")
+                                .long("kind")
+                                .default_value("post")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("title")
+                                .help("Title of the content, default to the one from the file name.")
+                                .short("t")
+                                .long("title")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("tags")
+                                .help("Tags of the content, separated by commas.")
+                                .long("tags")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("date")
+                                .help("Create time of the content, e.g. 2018-01-01. Default to now.")
+                                .long("date")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("force")
+                                .help("Overwrite the file if it exists.")
+                                .short("f")
+                                .long("force")
+                                .conflicts_with("no-clobber"),
+                        )
+                        .arg(
+                            Arg::with_name("no-clobber")
+                                .help("Keep the file if it exists.")
+                                .short("n")
+                                .long("no-clobber"),
+                        )
+                        .arg(
+                            Arg::with_name("edit")
+                                .help("Open the content with $VISUAL or $EDITOR.")
+                                .short("e")
+                                .long("edit"),
+                        )
                         .display_order(0),
                 )
                 .subcommand(
                     SubCommand::with_name("list")
-                        .about("List all contents.")
+                        .about("List all contents, drafts included.")
+                        .arg(
+                            Arg::with_name("tag")
+                                .help("Only list the contents having the tag, can be used many times.")
+                                .long("tag")
+                                .takes_value(true)
+                                .multiple(true)
+                                .number_of_values(1),
+                        )
+                        .arg(
+                            Arg::with_name("target")
+                                .help("Only list the contents of the target.")
+                                .long("target")
+                                .possible_values(&["all", "post", "draft"])
+                                .default_value("all")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("since")
+                                .help("Only list the contents created since the date, e.g. 2018-01-01.")
+                                .long("since")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("search")
+                                .help("Only list the contents containing the text.")
+                                .long("search")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("sort")
+                                .help("Sort the contents by the key, the newest first for date.")
+                                .long("sort")
+                                .possible_values(&["date", "title", "path", "target", "id"])
+                                .default_value("date")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("reverse")
+                                .help("Reverse the order.")
+                                .long("reverse"),
+                        )
+                        .arg(
+                            Arg::with_name("columns")
+                                .help("Columns to show, separated by commas.")
+                                .long("columns")
+                                .possible_values(LIST_COLUMNS)
+                                .use_delimiter(true)
+                                .default_value("title,date,target,id")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("format")
+                                .help("Format of the list.")
+                                .long("format")
+                                .possible_values(&["table", "json", "csv"])
+                                .default_value("table")
+                                .takes_value(true),
+                        )
                         .display_order(1),
                 )
+                .subcommand(
+                    SubCommand::with_name("publish")
+                        .about("Set the target of a content to POST, and the create time to now.")
+                        .arg(
+                            Arg::with_name("CONTENT")
+                                .help("Path or id of the content.")
+                                .required(true),
+                        )
+                        .display_order(2),
+                )
+                .subcommand(
+                    SubCommand::with_name("unpublish")
+                        .about("Set the target of a content to DRAFT.")
+                        .arg(
+                            Arg::with_name("CONTENT")
+                                .help("Path or id of the content.")
+                                .required(true),
+                        )
+                        .display_order(3),
+                )
+                .subcommand(
+                    SubCommand::with_name("mv")
+                        .about("Move a content, the id is kept and the old path is recorded in aliases.")
+                        .arg(
+                            Arg::with_name("CONTENT")
+                                .help("Path or id of the content.")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("PATH")
+                                .help("The new path of the content.")
+                                .required(true),
+                        )
+                        .display_order(4),
+                )
+                .subcommand(
+                    SubCommand::with_name("rm")
+                        .about("Remove a content.")
+                        .arg(
+                            Arg::with_name("CONTENT")
+                                .help("Path or id of the content.")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("yes")
+                                .help("Remove without asking.")
+                                .short("y")
+                                .long("yes"),
+                        )
+                        .display_order(5),
+                )
+                .subcommand(
+                    SubCommand::with_name("fix-ids")
+                        .about("Give a new id to the contents whose id is copied from an older content.")
+                        .display_order(6),
+                )
                 .display_order(6),
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+                .about("Import the posts of another static site generator.")
+                .arg(
+                    Arg::with_name("FORMAT")
+                        .help("The generator of the posts.")
+                        .possible_values(&["hexo", "jekyll", "hugo", "wordpress"])
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("SOURCE")
+                        .help("The root of the site to import, or the export file of WordPress.")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("attachments")
+                        .help("A local mirror of wp-content/uploads, the attachments are copied from it.")
+                        .long("attachments")
+                        .value_name("DIR")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("force")
+                        .help("Overwrite the existing contents.")
+                        .short("f")
+                        .long("force"),
+                )
+                .display_order(8),
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Export the contents to another static site generator.")
+                .arg(
+                    Arg::with_name("format")
+                        .help("Format of the contents, json writes the render model of the templates.")
+                        .long("format")
+                        .possible_values(&["hugo", "jekyll", "json"])
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("DIRECTORY")
+                        .help("The directory to write.")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("force")
+                        .help("Write into the directory even if it is not empty.")
+                        .short("f")
+                        .long("force"),
+                )
+                .display_order(9),
+        )
+        .subcommand(
+            SubCommand::with_name("tui")
+                .about("Manage the contents in the terminal, the same as running without a command.")
+                .arg(
+                    Arg::with_name("PATH")
+                        .help("The root of the site. Default use --source or the current directory."),
+                )
+                .display_order(10),
         );
     let matches = app.get_matches();
+    if let Err(error) = init_log(&matches) {
+        println!("{0:>12} {1}", "Error".red().bold(), error);
+        process::exit(error.kind.exit_code());
+    }
     handle_matches(matches);
 }
-fn handle_matches(matches: clap::ArgMatches) {
-    if let Some(_) = matches.subcommand_matches("init") {
-        if let Err(error) = site_command::init() {
+
+/// Get the value of a global option, which may be given after any of the subcommands.
+fn global_value<'a>(matches: &'a ArgMatches, name: &str) -> Option<&'a str> {
+    let mut value = matches.value_of(name);
+    let mut current = matches;
+    while let (_, Some(matches)) = current.subcommand() {
+        value = matches.value_of(name).or(value);
+        current = matches;
+    }
+    return value;
+}
+
+/// Get the times a global flag is given, counted on the command or one of the subcommands.
+fn global_occurrences(matches: &ArgMatches, name: &str) -> u64 {
+    let mut occurrences = matches.occurrences_of(name);
+    let mut current = matches;
+    while let (_, Some(matches)) = current.subcommand() {
+        occurrences = cmp::max(occurrences, matches.occurrences_of(name));
+        current = matches;
+    }
+    return occurrences;
+}
+
+/// Set the colors and the log by `--color`, `-v`, `-q` and `--log-file`.
+fn init_log(matches: &ArgMatches) -> Result<(), Error> {
+    let color = match global_value(matches, "color").unwrap_or("auto") {
+        "always" => true,
+        "never" => false,
+        _ => atty::is(atty::Stream::Stdout),
+    };
+    control::set_override(color);
+    let verbosity =
+        global_occurrences(matches, "verbose") as i64 - global_occurrences(matches, "quiet") as i64;
+    let level = match verbosity {
+        v if v <= -2 => LevelFilter::Error,
+        -1 => LevelFilter::Warn,
+        0 => LevelFilter::Info,
+        1 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+    // Always on the stderr, the terminal logger writes the warnings to the stdout, which
+    // would break the json and csv outputs of the commands.
+    let mut loggers: Vec<Box<SharedLogger>> =
+        vec![WriteLogger::new(level, Config::default(), io::stderr())];
+    if let Some(log_file) = global_value(matches, "log-file") {
+        let file = File::create(log_file).map_err(|err| {
+            Error::new("Failed to create the log file.")
+                .with_kind(ErrorKind::Io)
+                .with_path(log_file)
+                .with_source(err)
+        })?;
+        let file_level = cmp::max(level, LevelFilter::Debug);
+        loggers.push(WriteLogger::new(file_level, Config::default(), file));
+    }
+    CombinedLogger::init(loggers)
+        .map_err(|err| Error::new("Failed to initialize the log.").with_source(err))?;
+    return Ok(());
+}
+fn values_of(matches: &ArgMatches, name: &str) -> Vec<String> {
+    return matches
+        .values_of(name)
+        .map(|values| values.map(|x| x.to_string()).collect())
+        .unwrap_or(vec![]);
+}
+
+/// Print the result of a command, exit with the code of the error kind if it failed.
+fn finish(result: Result<(), Error>) {
+    match result {
+        Ok(_) => println!("{0:>12}", "Finished".green().bold()),
+        Err(error) => {
             error!("{}", error);
-        } else {
-            println!("{0:>12}", "Finished".green().bold());
+            process::exit(error.kind.exit_code());
         }
+    }
+}
+
+fn handle_matches(matches: clap::ArgMatches) {
+    let source = SiteSource {
+        root: global_value(&matches, "source").unwrap_or(".").to_string(),
+        config: global_value(&matches, "config").map(|x| x.to_string()),
+    };
+    if let Some(_) = matches.subcommand_matches("init") {
+        finish(site_command::init(&source));
         return;
     };
     if let Some(matches) = matches.subcommand_matches("new") {
         let path = matches.value_of("PATH").unwrap_or(".");
-        if let Err(error) = site_command::new(path) {
-            error!("{}", error);
-        } else {
-            println!("{0:>12}", "Finished".green().bold());
-        }
+        finish(site_command::new(path));
         return;
     };
     if let Some(_) = matches.subcommand_matches("info") {
-        if let Err(error) = site_command::info(".") {
-            error!("{}", error);
-        } else {
-            println!("{0:>12}", "Finished".green().bold());
+        finish(site_command::info(&source));
+        return;
+    };
+    if let Some(matches) = matches.subcommand_matches("build") {
+        let minify = matches.is_present("minify");
+        let clean = matches.is_present("clean");
+        let strict = matches.is_present("strict");
+        let jobs = match matches.value_of("jobs").unwrap_or("0").parse::<usize>() {
+            Ok(jobs) => jobs,
+            Err(err) => {
+                return finish(Err(Error::new("--jobs must be a non-negative integer.")
+                    .with_source(err)
+                    .with_kind(ErrorKind::Config)));
+            }
+        };
+        let report = matches.value_of("report").unwrap_or("text");
+        let report_file = matches.value_of("report-file");
+        if report_file.is_some() && report != "json" {
+            return finish(Err(Error::new("--report-file is only available with --report json.")
+                .with_kind(ErrorKind::Config)));
+        }
+        let result = site_command::build(&source, minify, clean, strict, jobs, report, report_file);
+        // Keep the json report on the stdout clean for the scripts.
+        if report != "json" || report_file.is_some() || result.is_err() {
+            finish(result);
         }
         return;
     };
-    if let Some(_) = matches.subcommand_matches("build") {
-        if let Err(error) = site_command::build(".") {
-            error!("{}", error);
-        } else {
-            println!("{0:>12}", "Finished".green().bold());
+    if let Some(matches) = matches.subcommand_matches("check") {
+        if let Some(matches) = matches.subcommand_matches("links") {
+            finish(site_command::check_links(&source, matches.value_of("allowlist")));
+            return;
         }
+        finish(site_command::check(&source));
         return;
     };
     if let Some(_) = matches.subcommand_matches("publish") {
-        if let Err(error) = site_command::publish(".") {
-            error!("{}", error);
-        } else {
-            println!("{0:>12}", "Finished".green().bold());
-        }
+        finish(site_command::publish(&source));
         return;
     };
     if let Some(matches) = matches.subcommand_matches("server") {
@@ -127,34 +549,114 @@ fn handle_matches(matches: clap::ArgMatches) {
             .unwrap_or("8765")
             .parse::<u64>()
             .unwrap_or(8765);
-        if let Err(error) = site_command::server(".", port) {
-            error!("{}", error);
-        } else {
-            println!("{0:>12}", "Finished".green().bold());
-        }
+        finish(site_command::server(&source, port));
+        return;
+    };
+    if let Some(matches) = matches.subcommand_matches("import") {
+        let format = matches.value_of("FORMAT").unwrap_or("hexo");
+        let source_path = matches.value_of("SOURCE").unwrap_or(".");
+        let attachments = matches.value_of("attachments");
+        finish(content_command::import(&source, format, source_path, attachments, matches.is_present("force")));
+        return;
+    };
+    if let Some(matches) = matches.subcommand_matches("export") {
+        let format = matches.value_of("format").unwrap_or("json");
+        let directory = matches.value_of("DIRECTORY").unwrap_or(".");
+        finish(content_command::export(&source, format, directory, matches.is_present("force")));
         return;
     };
     if let Some(matches) = matches.subcommand_matches("content") {
         if let Some(matches) = matches.subcommand_matches("new") {
             let path = matches.value_of("PATH").unwrap_or(".");
-            if let Err(error) = content_command::new(".", path) {
-                error!("{}", error);
+            let date = match matches.value_of("date").map(Content::parse_date) {
+                Some(Ok(date)) => Some(date),
+                Some(Err(error)) => return finish(Err(error)),
+                None => None,
+            };
+            let overwrite = if matches.is_present("force") {
+                Overwrite::Force
+            } else if matches.is_present("no-clobber") {
+                Overwrite::Never
             } else {
-                println!("{0:>12}", "Finished".green().bold());
-            }
+                Overwrite::Ask
+            };
+            let options = NewOptions {
+                kind: matches.value_of("kind").unwrap_or("post").to_string(),
+                title: matches.value_of("title").map(|x| x.to_string()),
+                tags: matches.value_of("tags").map(|tags| {
+                    tags.split(',')
+                        .map(|x| x.trim().to_string())
+                        .filter(|x| !x.is_empty())
+                        .collect()
+                }),
+                date: date,
+                draft: matches.is_present("draft"),
+                overwrite: overwrite,
+            };
+            finish(content_command::new(&source, path, &options, matches.is_present("edit")));
             return;
         }
-        if let Some(_) = matches.subcommand_matches("list") {
-            if let Err(error) = content_command::list(".") {
-                error!("{}", error);
-            } else {
-                println!("{0:>12}", "Finished".green().bold());
+        if let Some(matches) = matches.subcommand_matches("list") {
+            let format = matches.value_of("format").unwrap_or("table");
+            let since = match matches.value_of("since").map(Content::parse_date) {
+                Some(Ok(date)) => Some(date),
+                Some(Err(error)) => return finish(Err(error)),
+                None => None,
+            };
+            let options = ListOptions {
+                tags: values_of(&matches, "tag"),
+                target: matches.value_of("target").filter(|x| *x != "all").map(|x| x.to_string()),
+                since: since,
+                search: matches.value_of("search").map(|x| x.to_string()),
+                sort: matches.value_of("sort").unwrap_or("date").to_string(),
+                reverse: matches.is_present("reverse"),
+                columns: values_of(&matches, "columns"),
+                format: format.to_string(),
+            };
+            let result = content_command::list(&source, &options);
+            // Keep the output of json and csv clean for the scripts.
+            if format == "table" || result.is_err() {
+                finish(result);
             }
             return;
         }
+        if let Some(matches) = matches.subcommand_matches("publish") {
+            finish(content_command::publish(&source, matches.value_of("CONTENT").unwrap_or("")));
+            return;
+        }
+        if let Some(matches) = matches.subcommand_matches("unpublish") {
+            finish(content_command::unpublish(&source, matches.value_of("CONTENT").unwrap_or("")));
+            return;
+        }
+        if let Some(matches) = matches.subcommand_matches("mv") {
+            let target = matches.value_of("CONTENT").unwrap_or("");
+            let path = matches.value_of("PATH").unwrap_or("");
+            finish(content_command::rename(&source, target, path));
+            return;
+        }
+        if let Some(matches) = matches.subcommand_matches("rm") {
+            let target = matches.value_of("CONTENT").unwrap_or("");
+            finish(content_command::remove(&source, target, matches.is_present("yes")));
+            return;
+        }
+        if let Some(_) = matches.subcommand_matches("fix-ids") {
+            finish(content_command::fix_ids(&source));
+            return;
+        }
         println!("{}", matches.usage());
         return;
     };
+    if let Some(matches) = matches.subcommand_matches("tui") {
+        let source = match matches.value_of("PATH") {
+            Some(path) => SiteSource {
+                root: path.to_string(),
+                ..source
+            },
+            None => source,
+        };
+        app::run(&source);
+        return;
+    };
     // println!("{}", matches.usage());
-    app::run();
+    app::run(&source);
 }
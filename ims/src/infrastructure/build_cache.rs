@@ -0,0 +1,146 @@
+//! # Record the dependencies of the build outputs, so that the unchanged outputs can be skipped.
+
+use std;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use serde_json;
+
+use super::util::normalize_output_path;
+use super::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Name of the cache file in the build directory.
+pub const BUILD_CACHE_FILE: &str = ".ims-cache.json";
+
+/// Version of the cache format, a cache of another version is ignored.
+pub const BUILD_CACHE_VERSION: u32 = 1;
+
+/// Dependencies of a rendered output.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct OutputRecord {
+    /// Hash of everything the output was rendered from.
+    pub fingerprint: String,
+    /// The template which wrote the output.
+    pub template: String,
+    /// The partials used by the template.
+    pub partials: Vec<String>,
+    /// Id of the content the output was rendered with, if any.
+    pub content: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct BuildCache {
+    /// Version of the cache format, missing in the caches older than the version 1.
+    #[serde(default)]
+    pub version: u32,
+    /// Rendered outputs, keyed by the path relative to the build directory.
+    #[serde(default)]
+    pub outputs: HashMap<String, OutputRecord>,
+    /// Hash of the theme files copied to the build directory.
+    #[serde(default)]
+    pub assets: HashMap<String, String>,
+}
+
+impl BuildCache {
+    pub fn new() -> BuildCache {
+        return BuildCache {
+            version: BUILD_CACHE_VERSION,
+            ..BuildCache::default()
+        };
+    }
+
+    /// Load the cache, an empty cache is returned if it is missing, broken or of another version.
+    pub fn load(path: &Path) -> BuildCache {
+        let mut buffer = String::new();
+        let result = fs::File::open(path).and_then(|mut file| file.read_to_string(&mut buffer));
+        if result.is_err() {
+            return BuildCache::new();
+        }
+        return match serde_json::from_str::<BuildCache>(&buffer) {
+            Ok(ref cache) if cache.version != BUILD_CACHE_VERSION => {
+                info!("Ignored the build cache of the version {}.", cache.version);
+                BuildCache::new()
+            }
+            Ok(mut cache) => {
+                // The outputs of the older versions are not normalized.
+                cache.outputs = cache
+                    .outputs
+                    .into_iter()
+                    .filter_map(|(name, record)| normalize_output_path(&name).map(|x| (x, record)))
+                    .collect();
+                cache
+            }
+            Err(error) => {
+                warn!("Ignored the broken build cache. error:{}", error);
+                BuildCache::new()
+            }
+        };
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let data = serde_json::to_string(&self).map_err(|err| {
+            Error::new("An error occurred while converting the build cache.").with_source(err)
+        })?;
+        let mut file = fs::File::create(path).map_err(|err| {
+            Error::new("An error occurred while creating the build cache.").with_source(err)
+        })?;
+        file.write_all(&data.into_bytes()).map_err(|err| {
+            Error::new("An error occurred while saving the build cache.").with_source(err)
+        })?;
+        return Ok(());
+    }
+}
+
+/// Hash the data with the 64-bit FNV-1a.
+///
+/// The hashes are stored in the cache, so the algorithm must not change between the builds
+/// (`DefaultHasher` may change between the Rust releases).
+pub fn hash_bytes(data: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in data {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    return format!("{:016x}", hash);
+}
+
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut buffer = vec![];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut buffer))
+        .map_err(|err| Error::new("Failed to read file.").with_source(err))?;
+    return Ok(hash_bytes(&buffer));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    #[test]
+    fn hash_bytes_is_fnv1a() {
+        assert_eq!(hash_bytes(b""), "cbf29ce484222325");
+        assert_eq!(hash_bytes(b"a"), "af63dc4c8601ec8c");
+        assert_eq!(hash_bytes(b"foobar"), "85944171f73967e8");
+    }
+
+    #[test]
+    fn load_ignores_the_cache_of_another_version() {
+        let path = env::temp_dir().join(format!("ims-cache-{}.json", process::id()));
+        let mut cache = BuildCache::new();
+        cache.assets.insert("a.css".to_string(), hash_bytes(b"a"));
+        cache.save(&path).unwrap();
+        assert_eq!(BuildCache::load(&path).assets.len(), 1);
+
+        fs::write(&path, r#"{"outputs":{},"assets":{"a.css":"0"}}"#).unwrap();
+        let cache = BuildCache::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(cache.version, BUILD_CACHE_VERSION);
+        assert!(cache.assets.is_empty());
+    }
+}
@@ -1,38 +1,143 @@
+use std::error;
 use std::fmt;
-use std::fmt::Display;
+use std::io;
+use std::sync::Arc;
+
+/// What went wrong, used to tell the errors apart and to choose the exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorKind {
+    /// The site config is missing or invalid.
+    Config,
+    /// The front matter of a content is missing or invalid.
+    FrontMatter,
+    /// A template has a syntax error or failed to render.
+    Template,
+    /// Reading or writing a file failed.
+    Io,
+    /// The build has failed in strict mode.
+    Build,
+    /// The server failed to launch.
+    Server,
+    Other,
+}
+
+impl ErrorKind {
+    /// Exit code of the process when a command failed with this kind of error.
+    pub fn exit_code(&self) -> i32 {
+        return match *self {
+            ErrorKind::Other => 1,
+            ErrorKind::Config => 3,
+            ErrorKind::FrontMatter => 4,
+            ErrorKind::Template => 5,
+            ErrorKind::Io => 6,
+            ErrorKind::Build => 7,
+            ErrorKind::Server => 8,
+        };
+    }
+}
+
+/// Where the error occurred.
+#[derive(Debug, Clone, Default)]
+pub struct Location {
+    pub path: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+        }
+        return Ok(());
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Error {
+    pub kind: ErrorKind,
     pub message: String,
-    pub inner_error: Box<Option<Error>>,
+    pub location: Option<Location>,
+    source: Option<Arc<error::Error + Send + Sync>>,
 }
 
 impl Error {
     pub fn new(message: &str) -> Error {
         return Error {
+            kind: ErrorKind::Other,
             message: message.to_string(),
-            inner_error: Box::new(None),
+            location: None,
+            source: None,
         };
     }
 
-    pub fn with_inner_error(mut self, error: &Display) -> Error {
-        let message = format!("{}", error);
-        self.inner_error = Box::new(Some(Error::new(&message)));
+    pub fn with_kind(mut self, kind: ErrorKind) -> Error {
+        self.kind = kind;
+        return self;
+    }
+
+    /// Set the error which caused this error.
+    ///
+    /// If the kind is not set yet, it is inherited from an `Error`, or set to `Io` for an
+    /// `io::Error`.
+    pub fn with_source<E: error::Error + Send + Sync + 'static>(mut self, error: E) -> Error {
+        if self.kind == ErrorKind::Other {
+            let source = &error as &(error::Error + 'static);
+            if let Some(inner) = source.downcast_ref::<Error>() {
+                self.kind = inner.kind;
+            } else if source.is::<io::Error>() {
+                self.kind = ErrorKind::Io;
+            }
+        }
+        self.source = Some(Arc::new(error));
         return self;
     }
+
+    pub fn with_path(mut self, path: &str) -> Error {
+        let mut location = self.location.take().unwrap_or_default();
+        location.path = path.to_string();
+        self.location = Some(location);
+        return self;
+    }
+
+    pub fn with_position(mut self, line: usize, column: usize) -> Error {
+        let mut location = self.location.take().unwrap_or_default();
+        location.line = Some(line);
+        location.column = Some(column);
+        self.location = Some(location);
+        return self;
+    }
+}
+
+impl Error {
+    /// The message and the sources, without the location.
+    pub fn reason(&self) -> String {
+        match self.source {
+            Some(ref source) => format!("{} -> {}", self.message, source),
+            None => self.message.clone(),
+        }
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if self.inner_error.is_some() {
-            return write!(
-                f,
-                "{} -> {}",
-                self.message,
-                self.inner_error.clone().unwrap()
-            );
-        } else {
-            return write!(f, "{}", self.message);
+        if let Some(ref location) = self.location {
+            write!(f, "{}: ", location)?;
         }
+        return write!(f, "{}", self.reason());
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        return &self.message;
+    }
+
+    fn source(&self) -> Option<&(error::Error + 'static)> {
+        return self.source.as_ref().map(|x| &**x as &(error::Error + 'static));
     }
 }
@@ -0,0 +1,226 @@
+//! # Edit the json mark info of a content as text.
+//!
+//! Only the changed values are replaced, so the order of the keys, the format and the
+//! unknown keys are kept.
+
+use std;
+
+use serde_json::{self, Value};
+
+use super::{Error, ErrorKind};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// A top level entry of the object, the positions are byte offsets.
+struct Entry {
+    key: String,
+    /// Start of the text between the previous `{` or `,` and the key.
+    lead: usize,
+    start: usize,
+    value_start: usize,
+    value_end: usize,
+}
+
+fn skip_whitespace(bytes: &[u8], mut index: usize) -> usize {
+    while index < bytes.len() && (bytes[index] as char).is_whitespace() {
+        index += 1;
+    }
+    return index;
+}
+
+/// Get the end of the string starting at the quote.
+fn end_of_string(bytes: &[u8], mut index: usize) -> Option<usize> {
+    index += 1;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'"' => return Some(index + 1),
+            _ => index += 1,
+        }
+    }
+    return None;
+}
+
+/// Get the end of the value, without the whitespaces after it.
+fn end_of_value(bytes: &[u8], mut index: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut end = index;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'"' => {
+                index = end_of_string(bytes, index)?;
+                end = index;
+                continue;
+            }
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' if depth == 0 => return Some(end),
+            b'}' | b']' => depth -= 1,
+            b',' if depth == 0 => return Some(end),
+            _ => {}
+        }
+        index += 1;
+        if !(bytes[index - 1] as char).is_whitespace() {
+            end = index;
+        }
+    }
+    return None;
+}
+
+/// Get the top level entries and the position of the closing brace.
+fn scan(mark: &str) -> Option<(Vec<Entry>, usize)> {
+    let bytes = mark.as_bytes();
+    let mut index = skip_whitespace(bytes, 0);
+    if bytes.get(index) != Some(&b'{') {
+        return None;
+    }
+    index += 1;
+    let mut entries = vec![];
+    loop {
+        let lead = index;
+        index = skip_whitespace(bytes, index);
+        match bytes.get(index) {
+            Some(&b'}') => return Some((entries, index)),
+            Some(&b'"') => {}
+            _ => return None,
+        }
+        let start = index;
+        index = end_of_string(bytes, index)?;
+        let key = serde_json::from_str::<String>(&mark[start..index]).ok()?;
+        index = skip_whitespace(bytes, index);
+        if bytes.get(index) != Some(&b':') {
+            return None;
+        }
+        let value_start = skip_whitespace(bytes, index + 1);
+        let value_end = end_of_value(bytes, value_start)?;
+        entries.push(Entry {
+            key: key,
+            lead: lead,
+            start: start,
+            value_start: value_start,
+            value_end: value_end,
+        });
+        index = skip_whitespace(bytes, value_end);
+        match bytes.get(index) {
+            Some(&b',') => index += 1,
+            Some(&b'}') => return Some((entries, index)),
+            _ => return None,
+        }
+    }
+}
+
+/// Get the top level keys and their byte offsets, empty if the mark info is not a valid object.
+pub fn mark_key_offsets(mark: &str) -> Vec<(String, usize)> {
+    return match scan(mark) {
+        Some((entries, _)) => entries.into_iter().map(|x| (x.key, x.start)).collect(),
+        None => vec![],
+    };
+}
+
+/// Set the value of a top level key, the key is added after the last one if it is missing.
+pub fn set_mark_value(mark: &str, key: &str, value: &Value) -> Result<String> {
+    let (entries, close) = scan(mark).ok_or(
+        Error::new("The mark info is not a valid json object.").with_kind(ErrorKind::FrontMatter),
+    )?;
+    let value = value.to_string();
+    if let Some(entry) = entries.iter().find(|x| x.key == key) {
+        let (head, tail) = (&mark[..entry.value_start], &mark[entry.value_end..]);
+        return Ok(format!("{}{}{}", head, value, tail));
+    }
+    let entry = format!("{}: {}", Value::from(key), value);
+    return Ok(match entries.last() {
+        Some(last) => {
+            // Use the same indent as the last entry.
+            let lead = &mark[last.lead..last.start];
+            format!("{},{}{}{}", &mark[..last.value_end], lead, entry, &mark[last.value_end..])
+        }
+        None => format!("{}{}{}", &mark[..close], entry, &mark[close..]),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(mark: &str, key: &str, value: Value) -> String {
+        return set_mark_value(mark, key, &value).unwrap();
+    }
+
+    #[test]
+    fn set_mark_value_keeps_the_rest_byte_for_byte() {
+        let mark = "\n{\n  \"title\":\"A\",\n    \"target\" :  \"DRAFT\" ,\n  \"tags\": [ \"a\",\"b\" ]\n}\n";
+        assert_eq!(
+            set(mark, "target", json!("POST")),
+            "\n{\n  \"title\":\"A\",\n    \"target\" :  \"POST\" ,\n  \"tags\": [ \"a\",\"b\" ]\n}\n"
+        );
+        assert_eq!(
+            set(mark, "tags", json!(["c"])),
+            "\n{\n  \"title\":\"A\",\n    \"target\" :  \"DRAFT\" ,\n  \"tags\": [\"c\"]\n}\n"
+        );
+    }
+
+    #[test]
+    fn set_mark_value_keeps_the_unknown_and_nested_keys() {
+        let mark = r#"{"meta": {"target": "x", "deep": {"a": [1, {"b": "}"}]}}, "custom": 1, "target": "DRAFT"}"#;
+        assert_eq!(
+            set(mark, "target", json!("POST")),
+            r#"{"meta": {"target": "x", "deep": {"a": [1, {"b": "}"}]}}, "custom": 1, "target": "POST"}"#
+        );
+        assert_eq!(
+            set(mark, "custom", json!({"a": null})),
+            r#"{"meta": {"target": "x", "deep": {"a": [1, {"b": "}"}]}}, "custom": {"a":null}, "target": "DRAFT"}"#
+        );
+    }
+
+    #[test]
+    fn set_mark_value_skips_the_separators_in_strings() {
+        let mark = r#"{"title": "a, b} \"c\" \\", "target": "DRAFT"}"#;
+        assert_eq!(
+            set(mark, "target", json!("POST")),
+            r#"{"title": "a, b} \"c\" \\", "target": "POST"}"#
+        );
+        let title = "x\", \"target\": \"POST\"}";
+        let replaced = set(mark, "title", json!(title));
+        let value: Value = serde_json::from_str(&replaced).unwrap();
+        assert_eq!(value, json!({"title": title, "target": "DRAFT"}));
+    }
+
+    #[test]
+    fn set_mark_value_keeps_the_non_ascii_text() {
+        let mark = "{\"title\": \"静态博客\", \"description\": \"café 🎉\", \"target\": \"DRAFT\"}";
+        assert_eq!(
+            set(mark, "target", json!("POST")),
+            "{\"title\": \"静态博客\", \"description\": \"café 🎉\", \"target\": \"POST\"}"
+        );
+        assert_eq!(
+            set(mark, "description", json!("中文")),
+            "{\"title\": \"静态博客\", \"description\": \"中文\", \"target\": \"DRAFT\"}"
+        );
+    }
+
+    #[test]
+    fn set_mark_value_appends_a_missing_key() {
+        let mark = "{\n    \"title\": \"A\",\n    \"meta\": {\"aliases\": 1}\n}";
+        assert_eq!(
+            set(mark, "aliases", json!(["a.md"])),
+            "{\n    \"title\": \"A\",\n    \"meta\": {\"aliases\": 1},\n    \"aliases\": [\"a.md\"]\n}"
+        );
+        assert_eq!(set(" { } ", "target", json!("POST")), " { \"target\": \"POST\"} ");
+    }
+
+    #[test]
+    fn mark_key_offsets_skips_the_nested_keys() {
+        let mark = "{\n  \"meta\": {\"title\": \"\\\"title\\\":\"},\n  \"title\": \"A\"\n}";
+        let offsets = mark_key_offsets(mark);
+        assert_eq!(offsets, vec![("meta".to_string(), 4), ("title".to_string(), 39)]);
+        assert!(mark[39..].starts_with("\"title\": \"A\""));
+        assert!(mark_key_offsets("[1]").is_empty());
+    }
+
+    #[test]
+    fn set_mark_value_rejects_what_is_not_an_object() {
+        for mark in &["", "[1]", "{\"a\" 1}", "{\"a\": 1", "{\"a\": \"1}", "{a: 1}"] {
+            let err = set_mark_value(mark, "target", &json!("POST")).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::FrontMatter, "{}", mark);
+        }
+    }
+}
@@ -1,16 +1,21 @@
 //! # Contain some helper of the handlebars
 
 use chrono::prelude::*;
-use handlebars::{Handlebars, Helper, RenderContext, RenderError, Renderable};
-use pulldown_cmark::{html, Event, Parser, Tag};
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, RenderContext, RenderError, Renderable, StringWriter,
+};
 use serde::Serialize;
 use serde_json;
 use serde_json::Value;
-use std::borrow::Cow;
-use std::collections::hash_map::DefaultHasher;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
-use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use super::build_cache::{hash_bytes, OutputRecord};
+use super::markdown::render_markdown;
+use super::util::normalize_output_path;
 
 /// # Render the json Format of the object.
 ///
@@ -31,10 +36,8 @@ pub fn hash_helper(h: &Helper, _: &Handlebars, rc: &mut RenderContext) -> Result
         let json = param.value();
         if json.is_string() {
             let data = json.as_str().unwrap();
-            let mut state = DefaultHasher::new();
-            data.hash(&mut state);
-            let result = state.finish();
-            rc.writer.write(&result.to_string().into_bytes()).is_ok();
+            let result = hash_bytes(data.as_bytes());
+            rc.writer.write(&result.into_bytes()).is_ok();
         }
     }
     Ok(())
@@ -75,16 +78,10 @@ pub fn count_helper(h: &Helper, _: &Handlebars, rc: &mut RenderContext) -> Resul
     Ok(())
 }
 
-fn hash(data: String) -> String {
-    let mut state = DefaultHasher::new();
-    data.hash(&mut state);
-    let result = state.finish();
-    return result.to_string();
-}
-
 /// # Convert markdown to html.
 ///
-/// A helper for handlebars
+/// A helper for handlebars.
+/// The html of a content is also available as `html` in the model.
 pub fn markdown_helper(
     h: &Helper,
     _: &Handlebars,
@@ -94,58 +91,17 @@ pub fn markdown_helper(
         let json = param.value();
         if json.is_string() {
             let document = json.as_str().unwrap();
-            let parser = Parser::new(document);
-            let mut header_level = -1;
-            let parser = parser.map(|event| match event {
-                Event::Start(Tag::Header(level)) => {
-                    header_level = level;
-                    Event::Start(Tag::Header(level))
-                }
-                Event::Text(text) => {
-                    if header_level > -1 {
-                        let name = text.clone().into_owned();
-                        
-                        let data = Cow::from(format!(
-                            "<a id=\"anchor_{}\"></a>{}",
-                            hash(name.clone()),
-                            name
-                        ));
-                        Event::Html(data)
-                    } else {
-                        Event::Text(text)
-                    }
-                }
-                Event::End(Tag::Header(level)) => {
-                    header_level = -1;
-                    Event::End(Tag::Header(level))
-                }
-                _ => event,
-            });
-            let mut result = String::new();
-            html::push_html(&mut result, parser);
+            let (result, _) = render_markdown(document);
             rc.writer.write(&result.into_bytes()).is_ok();
         }
     }
     Ok(())
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct TocItem {
-    pub name: String,
-    pub level: i32,
-}
-impl TocItem {
-    pub fn new(name: &str, level: i32) -> TocItem {
-        return TocItem {
-            name: name.to_string(),
-            level: level,
-        };
-    }
-}
-
 /// # Get toc from markdown.
 ///
-/// A helper for handlebars
+/// A helper for handlebars.
+/// The toc of a content is also available as `toc` in the model.
 pub fn markdown_toc_helper(
     h: &Helper,
     r: &Handlebars,
@@ -155,44 +111,19 @@ pub fn markdown_toc_helper(
         let json = param.value();
         if json.is_string() {
             let document = json.as_str().unwrap();
-            let parser = Parser::new(document);
-            let mut header_level = -1;
-            let mut toc: Vec<TocItem> = vec![];
-            {
-                let parser = parser.map(|event| match event {
-                    Event::Start(Tag::Header(level)) => {
-                        header_level = level;
-                        Event::Start(Tag::Header(level))
-                    }
-                    Event::Text(text) => {
-                        if header_level > -1 {
-                            let name = text.clone().into_owned();
-                            let item = TocItem::new(&name, header_level);
-                            toc.push(item);
-                        }
-                        Event::Text(text)
-                    }
-                    Event::End(Tag::Header(level)) => {
-                        header_level = -1;
-                        Event::End(Tag::Header(level))
-                    }
-                    _ => event,
-                });
-                let mut result = String::new();
-                html::push_html(&mut result, parser);
-            }
+            let (_, toc) = render_markdown(document);
             let mut local_rc = rc.derive();
             if let Some(block_param) = h.block_param() {
                 let mut map = BTreeMap::new();
-                map.insert(block_param.to_string(), serde_json::to_value(&toc).unwrap());
-                local_rc.push_block_context(&map).unwrap();
+                map.insert(block_param.to_string(), serde_json::to_value(&toc)?);
+                local_rc.push_block_context(&map)?;
             } else {
-                local_rc.push_block_context(&toc).unwrap();
+                local_rc.push_block_context(&toc)?;
             }
             let template = h.template();
             match template {
                 Some(t) => {
-                    t.render(r, &mut local_rc).unwrap();
+                    t.render(r, &mut local_rc)?;
                 }
                 None => {}
             }
@@ -202,12 +133,8 @@ pub fn markdown_toc_helper(
     Ok(())
 }
 
-/// # Output content to file.
-///
-/// A helper for handlebars.
-pub fn file_helper(h: &Helper, r: &Handlebars, rc: &mut RenderContext) -> Result<(), RenderError> {
-    let template = h.template();
-
+/// The path of the `file` helper relative to the build directory, see `normalize_output_path`.
+fn file_path(h: &Helper) -> Result<String, RenderError> {
     let parms: Vec<String> = h
         .params()
         .iter()
@@ -224,11 +151,23 @@ pub fn file_helper(h: &Helper, r: &Handlebars, rc: &mut RenderContext) -> Result
             }
             return param;
         }).collect();
-    let file_path = parms.join("");
+    let path = parms.join("");
+    return normalize_output_path(&path).ok_or(RenderError::new(format!(
+        "Output path \"{}\" is empty or outside the build directory.",
+        path
+    )));
+}
+
+/// # Output content to file.
+///
+/// A helper for handlebars.
+pub fn file_helper(h: &Helper, r: &Handlebars, rc: &mut RenderContext) -> Result<(), RenderError> {
+    let template = h.template();
+    let file_path = file_path(h)?;
 
     match template {
         Some(t) => {
-            t.render(r, rc).unwrap();
+            t.render(r, rc)?;
         }
         None => {}
     }
@@ -237,6 +176,90 @@ pub fn file_helper(h: &Helper, r: &Handlebars, rc: &mut RenderContext) -> Result
         .is_ok();
     Ok(())
 }
+/// # Output content to file, the outputs not changed since the last build are skipped.
+///
+/// A helper for handlebars.
+/// The fingerprint of an output is the hash of the seed, the file path and the data of
+/// the block it is rendered in.
+pub struct IncrementalFileHelper {
+    seed: String,
+    record: OutputRecord,
+    previous: HashMap<String, String>,
+    outputs: Arc<Mutex<HashMap<String, OutputRecord>>>,
+}
+
+impl IncrementalFileHelper {
+    /// `previous` is the fingerprints of the outputs which still exist in the build directory.
+    pub fn new(
+        seed: &str,
+        template: &str,
+        partials: Vec<String>,
+        previous: HashMap<String, String>,
+    ) -> IncrementalFileHelper {
+        return IncrementalFileHelper {
+            seed: seed.to_string(),
+            record: OutputRecord {
+                fingerprint: String::new(),
+                template: template.to_string(),
+                partials: partials,
+                content: None,
+            },
+            previous: previous,
+            outputs: Arc::new(Mutex::new(HashMap::new())),
+        };
+    }
+
+    /// All the outputs met while rendering, including the skipped ones.
+    pub fn outputs(&self) -> Arc<Mutex<HashMap<String, OutputRecord>>> {
+        return self.outputs.clone();
+    }
+}
+
+impl HelperDef for IncrementalFileHelper {
+    fn call(&self, h: &Helper, r: &Handlebars, rc: &mut RenderContext) -> Result<(), RenderError> {
+        let file_path = file_path(h)?;
+        let mut record = self.record.clone();
+        {
+            let data = rc.evaluate("this")?;
+            let source = format!("{}\n{}\n{}", self.seed, file_path, data);
+            record.fingerprint = hash_bytes(source.as_bytes());
+            record.content = data
+                .get("id")
+                .and_then(|x| x.as_str())
+                .map(|x| x.to_string());
+        }
+        let unchanged = self.previous.get(&file_path) == Some(&record.fingerprint);
+        if let Ok(mut outputs) = self.outputs.lock() {
+            // A file written twice would overwrite the first output.
+            if let Some(other) = outputs.get(&file_path) {
+                if other.content.is_some() && other.content != record.content {
+                    return Err(RenderError::new(format!(
+                        "Output \"{}\" is generated by both content {} and {}.",
+                        file_path,
+                        other.content.as_ref().unwrap(),
+                        record.content.as_ref().map(|x| x.as_str()).unwrap_or("-")
+                    )));
+                }
+                return Err(RenderError::new(format!(
+                    "Output \"{}\" is generated twice by template {}.",
+                    file_path, record.template
+                )));
+            }
+            outputs.insert(file_path.clone(), record);
+        }
+        if unchanged {
+            let marker = format!("{}{}", writer::SKIPPED, file_path);
+            rc.writer.write_all(&marker.into_bytes()).is_ok();
+            return Ok(());
+        }
+        if let Some(t) = h.template() {
+            t.render(r, rc)?;
+        }
+        rc.writer.write_all(&file_path.into_bytes()).is_ok();
+        Ok(())
+    }
+}
+
 #[allow(unused)]
 pub fn pagination_helper(
     h: &Helper,
@@ -271,27 +294,27 @@ pub fn pagination_helper(
         let mut local_rc = rc.derive();
 
         // local_rc.set_local_var("@list".to_string(), serde_json::to_value(page).unwrap());
-        local_rc.set_local_var("@size".to_string(), serde_json::to_value(size).unwrap());
+        local_rc.set_local_var("@size".to_string(), serde_json::to_value(size)?);
         local_rc.set_local_var(
             "@index".to_string(),
-            serde_json::to_value(index + 1).unwrap(),
+            serde_json::to_value(index + 1)?,
         );
-        local_rc.set_local_var("@count".to_string(), serde_json::to_value(count).unwrap());
+        local_rc.set_local_var("@count".to_string(), serde_json::to_value(count)?);
         if let Some(block_param) = h.block_param() {
             let mut map = BTreeMap::new();
             map.insert(
                 block_param.to_string(),
-                serde_json::to_value(&page).unwrap(),
+                serde_json::to_value(&page)?,
             );
-            local_rc.push_block_context(&map).unwrap();
+            local_rc.push_block_context(&map)?;
         } else {
-            local_rc.push_block_context(&page).unwrap();
+            local_rc.push_block_context(&page)?;
         }
 
         // local_rc.push_block_context(&page).unwrap();
         match template {
             Some(t) => {
-                t.render(r, &mut local_rc).unwrap();
+                t.render(r, &mut local_rc)?;
             }
             None => {}
         }
@@ -316,6 +339,42 @@ pub trait HandlebarsExtension {
     ) -> Result<HashMap<String, String>, RenderError>
     where
         T: Serialize;
+    /// Same as `render_with_file`, but with the given `file` helper and without changing the
+    /// registry, so that one registry can be shared by the threads.
+    fn render_shared_with_file<T>(
+        &self,
+        name: &str,
+        data: &T,
+        helper: Box<HelperDef>,
+    ) -> Result<HashMap<String, String>, RenderError>
+    where
+        T: Serialize;
+    /// Render a template without escaping the values, e.g. for a plain text output.
+    fn render_unescaped<T>(&self, name: &str, data: &T) -> Result<String, RenderError>
+    where
+        T: Serialize;
+}
+
+/// Render a registered template with the local helpers, which take precedence over the
+/// registered ones.
+fn render_to_write_with<T>(
+    registry: &Handlebars,
+    name: &str,
+    data: &T,
+    helpers: &mut HashMap<String, Rc<Box<HelperDef>>>,
+    escape: bool,
+    writer: &mut Write,
+) -> Result<(), RenderError>
+where
+    T: Serialize,
+{
+    let template = registry
+        .get_template(name)
+        .ok_or(RenderError::new(format!("Template not found: {}", name)))?;
+    let mut rc = RenderContext::new(Context::wraps(data)?, helpers, writer);
+    rc.root_template = template.name.clone();
+    rc.disable_escape = !escape;
+    return template.render(registry, &mut rc);
 }
 
 impl HandlebarsExtension for Handlebars {
@@ -327,12 +386,12 @@ impl HandlebarsExtension for Handlebars {
     where
         T: Serialize,
     {
-        if self.get_template("file") == None {
+        if self.get_helper("file").is_none() {
             self.register_helper("file", Box::new(file_helper));
         }
 
         let mut write = writer::TemplateWriter::new();
-        self.render_to_write(name, data, &mut write).unwrap();
+        self.render_to_write(name, data, &mut write)?;
         return Ok(write.map);
     }
 
@@ -344,20 +403,49 @@ impl HandlebarsExtension for Handlebars {
     where
         T: Serialize,
     {
-        if self.get_template("file") == None {
+        if self.get_helper("file").is_none() {
             self.register_helper("file", Box::new(file_helper));
         }
         let mut write = writer::TemplateWriter::new();
         self.render_template_to_write(template_string, data, &mut write)
-            .unwrap();
+            .map_err(|err| RenderError::new(format!("{}", err)))?;
         return Ok(write.map);
     }
+
+    fn render_shared_with_file<T>(
+        &self,
+        name: &str,
+        data: &T,
+        helper: Box<HelperDef>,
+    ) -> Result<HashMap<String, String>, RenderError>
+    where
+        T: Serialize,
+    {
+        let mut helpers = HashMap::new();
+        helpers.insert("file".to_string(), Rc::new(helper));
+        let mut write = writer::TemplateWriter::new();
+        render_to_write_with(self, name, data, &mut helpers, true, &mut write)?;
+        return Ok(write.map);
+    }
+
+    fn render_unescaped<T>(&self, name: &str, data: &T) -> Result<String, RenderError>
+    where
+        T: Serialize,
+    {
+        let mut write = StringWriter::new();
+        render_to_write_with(self, name, data, &mut HashMap::new(), false, &mut write)?;
+        return Ok(write.to_string());
+    }
 }
 
 mod writer {
     use std::collections::HashMap;
     use std::io::prelude::*;
     use std::io::Result;
+
+    /// Prefix of the path written by a skipped `file` block.
+    pub const SKIPPED: &str = "\u{0}skipped:";
+
     pub struct TemplateWriter {
         pub map: HashMap<String, String>,
         pub buffer: Vec<u8>,
@@ -388,6 +476,10 @@ mod writer {
             let mut buffer: Vec<u8> = vec![];
             buffer.extend_from_slice(buf);
             let path = String::from_utf8(buffer).unwrap_or(String::new());
+            if path.starts_with(SKIPPED) {
+                self.buffer = vec![];
+                return Ok(());
+            }
             self.map.insert(
                 path.clone(),
                 String::from_utf8(self.buffer.clone()).unwrap_or(String::new()),
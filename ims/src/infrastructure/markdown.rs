@@ -0,0 +1,60 @@
+//! # Convert markdown to html.
+
+use pulldown_cmark::{html, Event, Parser, Tag};
+use std::borrow::Cow;
+
+use super::build_cache::hash_bytes;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TocItem {
+    pub name: String,
+    pub level: i32,
+}
+impl TocItem {
+    pub fn new(name: &str, level: i32) -> TocItem {
+        return TocItem {
+            name: name.to_string(),
+            level: level,
+        };
+    }
+}
+
+/// Same as the `hash` helper, so that the templates can link to the anchors.
+fn anchor(name: &str) -> String {
+    return hash_bytes(name.as_bytes());
+}
+
+/// Convert markdown to html and get the toc from it.
+///
+/// An anchor named `anchor_{hash of the text}` is added to each header.
+pub fn render_markdown(document: &str) -> (String, Vec<TocItem>) {
+    let parser = Parser::new(document);
+    let mut header_level = -1;
+    let mut toc: Vec<TocItem> = vec![];
+    let mut result = String::new();
+    {
+        let parser = parser.map(|event| match event {
+            Event::Start(Tag::Header(level)) => {
+                header_level = level;
+                Event::Start(Tag::Header(level))
+            }
+            Event::Text(text) => {
+                if header_level > -1 {
+                    let name = text.clone().into_owned();
+                    toc.push(TocItem::new(&name, header_level));
+                    let data = Cow::from(format!("<a id=\"anchor_{}\"></a>{}", anchor(&name), name));
+                    Event::Html(data)
+                } else {
+                    Event::Text(text)
+                }
+            }
+            Event::End(Tag::Header(level)) => {
+                header_level = -1;
+                Event::End(Tag::Header(level))
+            }
+            _ => event,
+        });
+        html::push_html(&mut result, parser);
+    }
+    return (result, toc);
+}
@@ -0,0 +1,496 @@
+//! # Minify the generated html/css/js files.
+
+use std::path::Path;
+
+/// Elements whose surrounding whitespace never affects the layout.
+const BLOCK_TAGS: &[&str] = &[
+    "!doctype", "html", "head", "body", "meta", "link", "title", "script", "style", "base",
+    "div", "p", "ul", "ol", "li", "dl", "dt", "dd", "section", "article", "header", "footer",
+    "nav", "main", "aside", "table", "thead", "tbody", "tfoot", "tr", "td", "th", "form",
+    "h1", "h2", "h3", "h4", "h5", "h6", "br", "hr", "blockquote", "figure", "figcaption",
+    "pre", "textarea", "noscript",
+];
+
+/// Minify the content by the extension of the file name.
+///
+/// Files that are not html/css/js are returned unchanged.
+pub fn minify_file(name: &str, content: &str) -> String {
+    let extension = Path::new(name)
+        .extension()
+        .and_then(|x| x.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    match extension.as_ref() {
+        "html" | "htm" => minify_html(content),
+        "css" => minify_css(content),
+        "js" => minify_js(content),
+        _ => content.to_string(),
+    }
+}
+
+fn find_ignore_case(text: &str, pattern: &str) -> Option<usize> {
+    let lower = text.to_ascii_lowercase();
+    lower.find(&pattern.to_ascii_lowercase())
+}
+
+/// Get the lowercase name of the tag at the start of the text.
+fn tag_name(text: &str) -> String {
+    let text = text.trim_start_matches('<').trim_start_matches('/');
+    text.chars()
+        .take_while(|c| !c.is_whitespace() && *c != '>' && *c != '/')
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Find the end of the tag at the start of the text, quoted attribute values are skipped.
+fn tag_end(text: &str) -> usize {
+    let mut quote: Option<char> = None;
+    for (index, c) in text.char_indices() {
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+            }
+            None => {
+                if c == '"' || c == '\'' {
+                    quote = Some(c);
+                } else if c == '>' {
+                    return index + 1;
+                }
+            }
+        }
+    }
+    return text.len();
+}
+
+/// Collapse the whitespace inside a tag, quoted attribute values are kept as is.
+fn minify_tag(tag: &str) -> String {
+    let mut result = String::with_capacity(tag.len());
+    let mut quote: Option<char> = None;
+    let mut space = false;
+    for c in tag.chars() {
+        if let Some(q) = quote {
+            result.push(c);
+            if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        if c.is_whitespace() {
+            space = true;
+            continue;
+        }
+        if space {
+            if c != '>' && !(c == '/' && tag.ends_with("/>")) && !result.ends_with('=') && c != '='
+            {
+                result.push(' ');
+            }
+            space = false;
+        }
+        if c == '"' || c == '\'' {
+            quote = Some(c);
+        }
+        result.push(c);
+    }
+    return result;
+}
+
+/// Get the value of an attribute of the tag, an attribute without value is empty.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let inner = tag.trim_start_matches('<').trim_end_matches('>').trim_end_matches('/');
+    let mut chars = inner.chars().peekable();
+    // Skip the name of the tag.
+    while chars.peek().map_or(false, |c| !c.is_whitespace()) {
+        chars.next();
+    }
+    loop {
+        while chars.peek().map_or(false, |c| c.is_whitespace()) {
+            chars.next();
+        }
+        let mut key = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() || c == '=' {
+                break;
+            }
+            key.push(c);
+            chars.next();
+        }
+        if key.is_empty() && chars.peek().is_none() {
+            return None;
+        }
+        while chars.peek().map_or(false, |c| c.is_whitespace()) {
+            chars.next();
+        }
+        let mut value = String::new();
+        if chars.peek() == Some(&'=') {
+            chars.next();
+            while chars.peek().map_or(false, |c| c.is_whitespace()) {
+                chars.next();
+            }
+            match chars.peek().cloned() {
+                Some(quote) if quote == '"' || quote == '\'' => {
+                    chars.next();
+                    while let Some(c) = chars.next() {
+                        if c == quote {
+                            break;
+                        }
+                        value.push(c);
+                    }
+                }
+                _ => {
+                    while let Some(&c) = chars.peek() {
+                        if c.is_whitespace() {
+                            break;
+                        }
+                        value.push(c);
+                        chars.next();
+                    }
+                }
+            }
+        }
+        if key.eq_ignore_ascii_case(name) {
+            return Some(value);
+        }
+    }
+}
+
+/// Whether the script is js by its `type`, other types such as templates are kept as is.
+fn is_javascript(tag: &str) -> bool {
+    let kind = attribute(tag, "type").unwrap_or_default().trim().to_lowercase();
+    return kind.is_empty()
+        || kind == "module"
+        || kind.ends_with("/javascript")
+        || kind.ends_with("/ecmascript");
+}
+
+/// Minify html.
+///
+/// Comments are removed and whitespace is collapsed, the content of `pre` and `textarea`
+/// is kept as is, and the inline `script`/`style` are minified as js/css.
+pub fn minify_html(html: &str) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+    let mut last_tag = String::new();
+    while !rest.is_empty() {
+        if rest.starts_with("<!--") {
+            let end = rest[4..].find("-->").map_or(rest.len(), |index| index + 7);
+            let comment = &rest[..end];
+            // The conditional comments of IE are kept as is.
+            if comment.starts_with("<!--[if") || comment.contains("<![endif]") {
+                result.push_str(comment);
+            }
+            rest = &rest[end..];
+            continue;
+        }
+        if rest.starts_with('<') {
+            let end = tag_end(rest);
+            let tag = &rest[..end];
+            let name = tag_name(tag);
+            result.push_str(&minify_tag(tag));
+            rest = &rest[end..];
+            if !tag.starts_with("</") {
+                if let Some(raw) = ["pre", "textarea", "script", "style"]
+                    .iter()
+                    .find(|x| **x == name)
+                {
+                    let close = format!("</{}", raw);
+                    let index = find_ignore_case(rest, &close).unwrap_or(rest.len());
+                    let body = &rest[..index];
+                    match *raw {
+                        "script" if is_javascript(tag) => result.push_str(&minify_js(body)),
+                        "style" => result.push_str(&minify_css(body)),
+                        _ => result.push_str(body),
+                    }
+                    rest = &rest[index..];
+                }
+            }
+            last_tag = name;
+            continue;
+        }
+        let index = rest.find('<').unwrap_or(rest.len());
+        let text = &rest[..index];
+        rest = &rest[index..];
+        let mut collapsed = String::with_capacity(text.len());
+        let mut space = false;
+        for c in text.chars() {
+            if c.is_whitespace() {
+                space = true;
+            } else {
+                if space {
+                    collapsed.push(' ');
+                    space = false;
+                }
+                collapsed.push(c);
+            }
+        }
+        if collapsed.is_empty() {
+            // Whitespace only, it can be dropped when it is next to a block element.
+            let next_tag = tag_name(rest);
+            let at_edge = result.is_empty() || rest.is_empty();
+            if space
+                && !at_edge
+                && !BLOCK_TAGS.contains(&last_tag.as_ref())
+                && !BLOCK_TAGS.contains(&next_tag.as_ref())
+            {
+                result.push(' ');
+            }
+            continue;
+        }
+        if space {
+            collapsed.push(' ');
+        }
+        result.push_str(&collapsed);
+    }
+    return result;
+}
+
+/// Whether the whitespace before the char is significant.
+///
+/// The space before `:` is kept because `a :hover` differs from `a:hover`.
+fn css_needs_space(result: &str, c: char) -> bool {
+    match result.chars().last() {
+        Some(last) => !"{};,>:".contains(last) && !"{};,>".contains(c),
+        None => false,
+    }
+}
+
+/// Minify css.
+///
+/// Comments are removed and whitespace around the punctuation is dropped.
+pub fn minify_css(css: &str) -> String {
+    let mut result = String::with_capacity(css.len());
+    let mut chars = css.chars().peekable();
+    let mut space = false;
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            space = true;
+            continue;
+        }
+        if c == '/' && chars.peek() == Some(&'*') {
+            chars.next();
+            let mut last = ' ';
+            while let Some(c) = chars.next() {
+                if last == '*' && c == '/' {
+                    break;
+                }
+                last = c;
+            }
+            continue;
+        }
+        if space {
+            if css_needs_space(&result, c) {
+                result.push(' ');
+            }
+            space = false;
+        }
+        if c == '"' || c == '\'' {
+            result.push(c);
+            let mut escaped = false;
+            while let Some(s) = chars.next() {
+                result.push(s);
+                if escaped {
+                    escaped = false;
+                } else if s == '\\' {
+                    escaped = true;
+                } else if s == c {
+                    break;
+                }
+            }
+            continue;
+        }
+        if c == '}' && result.ends_with(';') {
+            result.pop();
+        }
+        result.push(c);
+    }
+    return result;
+}
+
+/// Whether a `/` after the text starts a regex literal rather than a division.
+fn regex_allowed(result: &str, last: char) -> bool {
+    if "(,=:[!&|?{};+-*%<>~^\n".contains(last) {
+        return true;
+    }
+    let word: String = result
+        .chars()
+        .rev()
+        .skip_while(|c| c.is_whitespace())
+        .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+        .collect();
+    return ["nruter", "foepyt", "esac", "od", "esle", "wen", "ni", "fo", "diov", "teleted", "worht"]
+        .contains(&word.as_str());
+}
+
+/// Minify js.
+///
+/// Only comments, indentation and blank lines are removed, line breaks are kept
+/// so that the automatic semicolon insertion is not affected.
+pub fn minify_js(js: &str) -> String {
+    let mut result = String::with_capacity(js.len());
+    let mut chars = js.chars().peekable();
+    let mut line_start = true;
+    // The last significant char, used to tell a regex literal from a division.
+    let mut last = '\n';
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' => continue,
+            '\n' => {
+                while result.ends_with(' ') || result.ends_with('\t') {
+                    result.pop();
+                }
+                if !line_start {
+                    result.push('\n');
+                }
+                line_start = true;
+                continue;
+            }
+            ' ' | '\t' if line_start => continue,
+            '/' if chars.peek() == Some(&'/') => {
+                while let Some(&next) = chars.peek() {
+                    if next == '\n' {
+                        break;
+                    }
+                    chars.next();
+                }
+                continue;
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                let mut multiline = false;
+                while let Some(next) = chars.next() {
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    multiline = multiline || next == '\n';
+                    previous = next;
+                }
+                // The comment still separates the tokens, and a line break ends a statement.
+                if multiline {
+                    while result.ends_with(' ') || result.ends_with('\t') {
+                        result.pop();
+                    }
+                    if !line_start {
+                        result.push('\n');
+                    }
+                    line_start = true;
+                } else if !line_start {
+                    while chars.peek() == Some(&' ') || chars.peek() == Some(&'\t') {
+                        chars.next();
+                    }
+                    if !result.ends_with(' ') && chars.peek() != Some(&'\n') {
+                        result.push(' ');
+                    }
+                }
+                continue;
+            }
+            '"' | '\'' | '`' => {
+                result.push(c);
+                let mut escaped = false;
+                while let Some(next) = chars.next() {
+                    result.push(next);
+                    if escaped {
+                        escaped = false;
+                    } else if next == '\\' {
+                        escaped = true;
+                    } else if next == c || (next == '\n' && c != '`') {
+                        break;
+                    }
+                }
+            }
+            '/' if regex_allowed(&result, last) => {
+                result.push(c);
+                let mut escaped = false;
+                let mut class = false;
+                while let Some(next) = chars.next() {
+                    result.push(next);
+                    if escaped {
+                        escaped = false;
+                    } else if next == '\\' {
+                        escaped = true;
+                    } else if next == '[' {
+                        class = true;
+                    } else if next == ']' {
+                        class = false;
+                    } else if (next == '/' && !class) || next == '\n' {
+                        break;
+                    }
+                }
+            }
+            _ => result.push(c),
+        }
+        line_start = false;
+        if !c.is_whitespace() {
+            last = c;
+        }
+    }
+    while result.ends_with('\n') || result.ends_with(' ') {
+        result.pop();
+    }
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_keeps_pre_and_textarea() {
+        let html = "<div>\n  <pre>  a\n   b </pre>\n  <textarea> x  y\n</textarea>\n</div>";
+        assert_eq!(
+            minify_html(html),
+            "<div><pre>  a\n   b </pre><textarea> x  y\n</textarea></div>"
+        );
+    }
+
+    #[test]
+    fn html_minifies_inline_script_and_style() {
+        let html = "<script>\n  var a = 1; // one\n  var b = 2;\n</script>\n<style>\n a { color: red; }\n</style>";
+        assert_eq!(
+            minify_html(html),
+            "<script>var a = 1;\nvar b = 2;</script><style>a{color:red}</style>"
+        );
+    }
+
+    #[test]
+    fn html_keeps_scripts_which_are_not_javascript() {
+        let html = "<script type=\"text/template\">\n  <p> {{a}} </p> // x\n</script>";
+        assert_eq!(minify_html(html), html);
+        let html = "<script data-type=\"text/template\">\n  a(); // x\n</script>";
+        assert_eq!(minify_html(html), "<script data-type=\"text/template\">a();</script>");
+        let html = "<script type='module'>\n  a(); // x\n</script>";
+        assert_eq!(minify_html(html), "<script type='module'>a();</script>");
+    }
+
+    #[test]
+    fn html_keeps_conditional_comments() {
+        let html = "<!-- note --><!--[if lt IE 9]><script src=\"a.js\"></script><![endif]-->";
+        assert_eq!(
+            minify_html(html),
+            "<!--[if lt IE 9]><script src=\"a.js\"></script><![endif]-->"
+        );
+        let html = "<!--[if !IE]><!--><p>a</p><!--<![endif]-->";
+        assert_eq!(minify_html(html), html);
+    }
+
+    #[test]
+    fn js_keeps_comment_markers_in_strings() {
+        let js = "var u = \"http://a.com\"; // c\nvar s = '/* not */';";
+        assert_eq!(minify_js(js), "var u = \"http://a.com\";\nvar s = '/* not */';");
+    }
+
+    #[test]
+    fn js_keeps_comment_markers_in_regex() {
+        let js = "var r = /https?:\\/\\//g; // c\nvar c = /[/*]+/;";
+        assert_eq!(minify_js(js), "var r = /https?:\\/\\//g;\nvar c = /[/*]+/;");
+        let js = "function f(x) {\n    return /\\/\\//.test(x);\n}";
+        assert_eq!(minify_js(js), "function f(x) {\nreturn /\\/\\//.test(x);\n}");
+    }
+
+    #[test]
+    fn js_keeps_a_line_break_for_multiline_comments() {
+        assert_eq!(minify_js("a = 1/* x\n y */b = 2"), "a = 1\nb = 2");
+        assert_eq!(minify_js("var/**/x = a /* b */ / 2"), "var x = a / 2");
+    }
+}
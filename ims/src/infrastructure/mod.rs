@@ -1,7 +1,17 @@
 mod util;
 mod handlerbars_extension;
 mod error;
+mod minify;
+mod build_cache;
+mod markdown;
+mod search;
+mod front_matter;
 
-pub use self::util::{copy_all_file, get_all_file};
+pub use self::util::{copy_all_file, get_all_file, normalize_output_path, open_in_editor};
 pub use self::handlerbars_extension::*;
-pub use self::error::Error;
+pub use self::error::{Error, ErrorKind};
+pub use self::minify::minify_file;
+pub use self::markdown::{render_markdown, TocItem};
+pub use self::front_matter::{mark_key_offsets, set_mark_value};
+pub use self::search::{hash_token, strip_html, tokenize};
+pub use self::build_cache::{hash_bytes, hash_file, BuildCache, OutputRecord, BUILD_CACHE_FILE};
@@ -0,0 +1,123 @@
+//! # Split text into search tokens.
+
+use regex::Regex;
+
+lazy_static! {
+    static ref MARKUP: Regex = Regex::new(r"(?is)<script.*?</script>|<style.*?</style>|<!--.*?-->|<[^>]*>").unwrap();
+}
+
+/// Whether the char is written without spaces between words, i.e. Chinese, Japanese and Korean.
+fn is_cjk(c: char) -> bool {
+    return match c {
+        '\u{3040}'..='\u{30ff}'
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{ac00}'..='\u{d7af}'
+        | '\u{f900}'..='\u{faff}'
+        | '\u{20000}'..='\u{2ffff}' => true,
+        _ => false,
+    };
+}
+
+fn flush_word(word: &mut String, tokens: &mut Vec<String>) {
+    if !word.is_empty() {
+        tokens.push(word.clone());
+        word.clear();
+    }
+}
+
+fn flush_run(run: &mut Vec<char>, tokens: &mut Vec<String>) {
+    if run.len() == 1 {
+        tokens.push(run[0].to_string());
+    }
+    for pair in run.windows(2) {
+        tokens.push(pair.iter().collect());
+    }
+    run.clear();
+}
+
+/// Split text into lowercase tokens.
+///
+/// Words are split on the chars which are not alphanumeric. CJK text has no spaces, so it
+/// is split into overlapping bigrams, e.g. `静态博客` into `静态`, `态博` and `博客`; a
+/// single CJK char is a token itself.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut word = String::new();
+    let mut run: Vec<char> = vec![];
+    for c in text.chars() {
+        if is_cjk(c) {
+            flush_word(&mut word, &mut tokens);
+            run.push(c);
+        } else if c.is_alphanumeric() {
+            flush_run(&mut run, &mut tokens);
+            word.extend(c.to_lowercase());
+        } else {
+            flush_word(&mut word, &mut tokens);
+            flush_run(&mut run, &mut tokens);
+        }
+    }
+    flush_word(&mut word, &mut tokens);
+    flush_run(&mut run, &mut tokens);
+    return tokens;
+}
+
+/// Get the text of the html, without the tags, scripts and styles.
+pub fn strip_html(html: &str) -> String {
+    let text = MARKUP.replace_all(html, " ");
+    let text = text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&");
+    return text.split_whitespace().collect::<Vec<&str>>().join(" ");
+}
+
+/// FNV-1a hash of the token, used to choose the shard of a token.
+///
+/// It is simple enough to be implemented again by the themes.
+pub fn hash_token(token: &str) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in token.bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    return hash;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_splits_cjk_into_bigrams() {
+        assert_eq!(tokenize("静态博客"), vec!["静态", "态博", "博客"]);
+        assert_eq!(tokenize("ひらがな"), vec!["ひら", "らが", "がな"]);
+        assert_eq!(tokenize("博"), vec!["博"]);
+        assert_eq!(tokenize("一，二 三"), vec!["一", "二", "三"]);
+    }
+
+    #[test]
+    fn tokenize_splits_mixed_text() {
+        assert_eq!(tokenize("Rust静态博客 v2.0"), vec!["rust", "静态", "态博", "博客", "v2", "0"]);
+        assert_eq!(tokenize("用Rust写"), vec!["用", "rust", "写"]);
+        assert_eq!(tokenize("Hello, WORLD! Ärger"), vec!["hello", "world", "ärger"]);
+        assert!(tokenize(" ,.! ").is_empty());
+    }
+
+    #[test]
+    fn strip_html_keeps_the_text() {
+        let html = "<p class=\"a\">a &amp; b</p>\n<script>if (x<y) {}</script><style>p {}</style>\
+                    <!-- <b>c</b> --><B>d</B>&lt;e&gt; &amp;lt;";
+        assert_eq!(strip_html(html), "a & b d <e> &lt;");
+    }
+
+    #[test]
+    fn hash_token_is_fnv1a() {
+        assert_eq!(hash_token(""), 0x811c_9dc5);
+        assert_eq!(hash_token("a"), 0xe40c_292c);
+        assert_eq!(hash_token("foobar"), 0xbf9c_f968);
+    }
+}
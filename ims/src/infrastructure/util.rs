@@ -1,6 +1,8 @@
 use std;
 use std::fs::*;
+use std::env;
 use std::path::Path;
+use std::process::Command;
 use super::Error;
 
 type Result<T> = std::result::Result<T, Error>;
@@ -11,10 +13,10 @@ pub fn get_all_file(path: &Path) -> Result<Vec<String>> {
         return Ok(vec![]);
     } else if path.is_dir() {
         for entry in read_dir(path)
-            .map_err(|err| Error::new("Failed to open Directory.").with_inner_error(&err))?
+            .map_err(|err| Error::new("Failed to open Directory.").with_source(err))?
         {
             let entry = entry
-                .map_err(|err| Error::new("Failed to open Directory.").with_inner_error(&err))?;
+                .map_err(|err| Error::new("Failed to open Directory.").with_source(err))?;
             let sub_path = entry.path();
             let mut sub_list = get_all_file(&sub_path)?;
             list.append(&mut sub_list);
@@ -46,7 +48,7 @@ pub fn copy_all_file<F: Fn(&Path, &Path) -> bool>(
             .create(target)
             .map_err(|error| {
                 Error::new("An error occurred while creating the target directory.")
-                    .with_inner_error(&error)
+                    .with_source(error)
             })?;
     }
     let files = get_all_file(&source)?;
@@ -59,7 +61,7 @@ pub fn copy_all_file<F: Fn(&Path, &Path) -> bool>(
             .map_err(|err| {
                 Error::new(&format!(
                     "The Path is not The child path of the parent path."
-                )).with_inner_error(&err)
+                )).with_source(err)
             })?;
         let target_file_path = target.join(path);
         if filter(&path, &target_file_path) {
@@ -71,12 +73,79 @@ pub fn copy_all_file<F: Fn(&Path, &Path) -> bool>(
                 .create(parent_path)
                 .map_err(|error| {
                     Error::new("An error occurred while creating the parent directory.")
-                        .with_inner_error(&error)
+                        .with_source(error)
                 })?;
             copy(&source_file_path, &target_file_path)
-                .map_err(|error| Error::new("Failed to copy file.").with_inner_error(&error))?;
+                .map_err(|error| Error::new("Failed to copy file.").with_source(error))?;
         }
     }
 
     return Ok(());
 }
+
+/// Normalize the path of an output relative to the build directory, e.g. `./post/../a.html`
+/// to `a.html`.
+///
+/// `None` is returned if the path is empty or it leaves the build directory.
+pub fn normalize_output_path(path: &str) -> Option<String> {
+    let mut parts: Vec<&str> = vec![];
+    for part in path.split(|x| x == '/' || x == '\\') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            _ if part.contains(':') => return None,
+            _ => parts.push(part),
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+    return Some(parts.join("/"));
+}
+
+/// Open the file with `$VISUAL` or `$EDITOR`, and wait until the editor exits.
+pub fn open_in_editor(path: &Path) -> Result<()> {
+    let editor = env::var("VISUAL")
+        .or(env::var("EDITOR"))
+        .unwrap_or(if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    // The editor may have arguments, e.g. `code --wait`.
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().ok_or(Error::new("The editor is empty."))?;
+    let status = Command::new(program)
+        .args(parts)
+        .arg(path)
+        .status()
+        .map_err(|err| Error::new(&format!("Failed to launch the editor \"{}\".", editor)).with_source(err))?;
+    if !status.success() {
+        return Err(Error::new(&format!("The editor exited with {}.", status)));
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_output_path_removes_the_dots_and_separators() {
+        assert_eq!(normalize_output_path("./post/../a.html"), Some("a.html".to_string()));
+        assert_eq!(normalize_output_path("post//a.html"), Some("post/a.html".to_string()));
+        assert_eq!(normalize_output_path("post\\.\\a.html"), Some("post/a.html".to_string()));
+        assert_eq!(normalize_output_path("./a/./b/"), Some("a/b".to_string()));
+        // An absolute path is still relative to the build directory.
+        assert_eq!(normalize_output_path("/post/a.html"), Some("post/a.html".to_string()));
+    }
+
+    #[test]
+    fn normalize_output_path_rejects_the_paths_outside_the_directory() {
+        assert_eq!(normalize_output_path("../a.html"), None);
+        assert_eq!(normalize_output_path("a/../../b"), None);
+        assert_eq!(normalize_output_path("a/.."), None);
+        assert_eq!(normalize_output_path("C:/a.html"), None);
+        assert_eq!(normalize_output_path("a\\C:b"), None);
+        assert_eq!(normalize_output_path(""), None);
+        assert_eq!(normalize_output_path("./"), None);
+    }
+}
@@ -1,12 +1,14 @@
+extern crate atty;
 extern crate chrono;
 extern crate clap;
 extern crate colored;
 extern crate handlebars;
 extern crate iron;
 #[macro_use]
+extern crate lazy_static;
+#[macro_use]
 extern crate log;
 extern crate mount;
-#[macro_use]
 extern crate prettytable;
 extern crate regex;
 extern crate serde;
@@ -14,11 +16,15 @@ extern crate serde;
 extern crate serde_derive;
 #[macro_use]
 extern crate serde_json;
+extern crate serde_yaml;
 extern crate cursive;
 extern crate pulldown_cmark;
+extern crate rayon;
 extern crate simplelog;
 extern crate staticfile;
+extern crate toml;
 extern crate uuid;
+extern crate xml;
 
 mod app;
 mod command;
@@ -26,22 +32,6 @@ mod command_app;
 mod infrastructure;
 mod model;
 
-use std::fs::File;
-
-use simplelog::*;
-
-fn init_log() {
-    CombinedLogger::init(vec![
-        TermLogger::new(LevelFilter::Info, Config::default()).unwrap(),
-        WriteLogger::new(
-            LevelFilter::Debug,
-            Config::default(),
-            File::create("msmusic.log").unwrap(),
-        ),
-    ])
-    .unwrap();
-}
 fn main() {
-    init_log();
     command_app::run();
 }
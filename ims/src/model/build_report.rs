@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+use colored::*;
+
+use super::super::infrastructure::Error;
+
+/// A file that was skipped or failed, and why.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReportItem {
+    pub path: String,
+    pub reason: String,
+}
+
+impl ReportItem {
+    pub fn new(path: &str, reason: &str) -> ReportItem {
+        return ReportItem {
+            path: path.to_string(),
+            reason: reason.to_string(),
+        };
+    }
+
+    /// Use the location of the error as the path if it has one.
+    pub fn from_error(path: &str, error: &Error) -> ReportItem {
+        let path = match error.location {
+            Some(ref location) => format!("{}", location),
+            None => path.to_string(),
+        };
+        return ReportItem::new(&path, &error.reason());
+    }
+}
+
+/// Time spent in each step of the build, in milliseconds.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct BuildTimings {
+    pub loading: u64,
+    pub rendering: u64,
+    pub writing: u64,
+    pub copying: u64,
+    pub total: u64,
+}
+
+/// Summary of a build.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct BuildReport {
+    /// Contents loaded and published.
+    pub posts: usize,
+    /// Contents not published, e.g. drafts.
+    pub drafts: usize,
+    pub tags: usize,
+    /// Outputs of the templates, written or unchanged.
+    pub pages: usize,
+    pub files_written: usize,
+    pub bytes_written: u64,
+    pub files_unchanged: usize,
+    pub assets_copied: usize,
+    pub skipped: Vec<ReportItem>,
+    pub failed: Vec<ReportItem>,
+    pub timings: BuildTimings,
+}
+
+pub fn milliseconds(duration: Duration) -> u64 {
+    return duration.as_secs() * 1000 + duration.subsec_nanos() as u64 / 1_000_000;
+}
+
+fn format_bytes(bytes: u64) -> String {
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    if bytes < 1024 * 1024 {
+        return format!("{:.1} KiB", bytes as f64 / 1024.0);
+    }
+    return format!("{:.1} MiB", bytes as f64 / 1024.0 / 1024.0);
+}
+
+impl BuildReport {
+    pub fn new() -> BuildReport {
+        return BuildReport::default();
+    }
+
+    pub fn print(&self) {
+        println!(
+            "{0:>12} {1} post(s), {2} draft(s), {3} tag(s)",
+            "Loaded".green().bold(),
+            self.posts,
+            self.drafts,
+            self.tags
+        );
+        println!(
+            "{0:>12} {1} file(s), {2}, {3} unchanged",
+            "Written".green().bold(),
+            self.files_written,
+            format_bytes(self.bytes_written),
+            self.files_unchanged
+        );
+        println!(
+            "{0:>12} {1} asset(s)",
+            "Copied".green().bold(),
+            self.assets_copied
+        );
+        for item in &self.skipped {
+            println!("{0:>12} {1}: {2}", "Skipped".yellow().bold(), item.path, item.reason);
+        }
+        for item in &self.failed {
+            println!("{0:>12} {1}: {2}", "Failed".red().bold(), item.path, item.reason);
+        }
+        println!(
+            "{0:>12} loading {1}ms, rendering {2}ms, writing {3}ms, copying {4}ms, total {5}ms",
+            "Time".green().bold(),
+            self.timings.loading,
+            self.timings.rendering,
+            self.timings.writing,
+            self.timings.copying,
+            self.timings.total
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use std;
+use std::collections::BTreeMap;
 use std::io;
 use std::io::{Read, Write};
 use std::fs;
@@ -9,10 +10,17 @@ use chrono::prelude::*;
 use uuid::Uuid;
 use regex::Regex;
 use colored::*;
-use prettytable::{format, Table};
+use prettytable::{format, Cell, Row, Table};
+use rayon::prelude::*;
+use handlebars::{no_escape, Handlebars};
+use atty;
 
-use super::Site;
-use super::super::infrastructure::{get_all_file, Error};
+use super::{BuildReport, Diagnostic, ReportItem, Site};
+
+use super::super::infrastructure::{
+    get_all_file, json_helper, mark_key_offsets, normalize_output_path, render_markdown, set_mark_value,
+    Error, ErrorKind, TocItem,
+};
 
 type Result<T> = std::result::Result<T, Error>;
 
@@ -29,6 +37,9 @@ fn default_description() -> String {
 fn default_target() -> String {
     "DRAFT".to_string()
 }
+fn default_kind() -> String {
+    "post".to_string()
+}
 fn default_tags() -> Vec<String> {
     vec![]
 }
@@ -42,16 +53,104 @@ fn default_content() -> String {
     "# Content \r\nmarkdown document.".to_string()
 }
 
+/// Columns of `ims content list`.
+pub const LIST_COLUMNS: &[&str] = &["id", "title", "description", "target", "kind", "tags", "date", "path"];
+
+/// What to do when the file of a new content exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overwrite {
+    /// Ask the user if the stdin is a terminal, otherwise fail.
+    Ask,
+    Force,
+    /// Keep the existing file.
+    Never,
+}
+
+/// Options of `ims content new`.
+#[derive(Debug, Clone)]
+pub struct NewOptions {
+    /// Kind of the content, selects the archetype.
+    pub kind: String,
+    pub title: Option<String>,
+    pub tags: Option<Vec<String>>,
+    /// The create time, default to now.
+    pub date: Option<DateTime<Utc>>,
+    /// Set the target to `DRAFT`.
+    pub draft: bool,
+    pub overwrite: Overwrite,
+}
+
+/// Filters and output of `ims content list`.
+#[derive(Debug, Clone)]
+pub struct ListOptions {
+    /// Only the contents having all the tags.
+    pub tags: Vec<String>,
+    /// Only the contents of the target, case insensitive.
+    pub target: Option<String>,
+    /// Only the contents created since the date.
+    pub since: Option<DateTime<Utc>>,
+    /// Only the contents containing the text, case insensitive.
+    pub search: Option<String>,
+    /// `date`, `title`, `path`, `target` or `id`.
+    pub sort: String,
+    pub reverse: bool,
+    /// Names in `LIST_COLUMNS`.
+    pub columns: Vec<String>,
+    /// `table`, `json` or `csv`.
+    pub format: String,
+}
+
+/// Get a title from the file name, e.g. `Hello World` from `posts/hello-world.md`.
+pub fn title_from_path(path: &str) -> String {
+    let name = Path::new(path)
+        .file_stem()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let words: Vec<String> = name
+        .split(|c| c == '-' || c == '_' || c == ' ')
+        .filter(|x| !x.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect();
+    return words.join(" ");
+}
+
+/// Ask the user, get whether the answer is yes.
+fn confirm(message: &str) -> Result<bool> {
+    print!("{}", message);
+    io::stdout()
+        .flush()
+        .map_err(|err| Error::new("Failed to write to the stdout.").with_source(err))?;
+    let mut buffer = String::new();
+    io::stdin()
+        .read_line(&mut buffer)
+        .map_err(|err| Error::new("Failed to read from the stdin.").with_source(err))?;
+    return Ok(buffer.trim().to_uppercase().starts_with("Y"));
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Content {
     #[serde(default = "default_id")] pub id: Uuid,
     #[serde(default = "default_title")] pub title: String,
     #[serde(default = "default_description")] pub description: String,
     #[serde(default = "default_target")] pub target: String,
+    /// Kind of the content, selects the `outputs` of the site.
+    #[serde(default = "default_kind")] pub kind: String,
     #[serde(default = "default_tags")] pub tags: Vec<String>,
     #[serde(default = "default_create_time")] pub create_time: DateTime<Utc>,
     #[serde(default = "default_meta")] pub meta: Value,
+    /// The old paths of the content, recorded by `ims content mv`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")] pub aliases: Vec<String>,
     #[serde(skip_deserializing, default = "default_content")] pub content: String,
+    /// The html of the content, set by `render`.
+    #[serde(skip_deserializing)] pub html: String,
+    /// The toc of the content, set by `render`.
+    #[serde(skip_deserializing)] pub toc: Vec<TocItem>,
     #[serde(skip)] pub path: String,
 }
 
@@ -61,37 +160,43 @@ impl Content {
         return content;
     }
 
-    /// Create a new Content,and save it to file.
-    pub fn new(site: &Site, path: &str) -> Result<Content> {
-        println!(
-            "{0:>12} {1} {2}",
-            "Creating".green().bold(),
-            "content",
-            path
-        );
+    /// Create a new Content from the archetype of the kind,and save it to file.
+    ///
+    /// The existing file is kept and loaded if `overwrite` is `Never`.
+    pub fn new(site: &Site, path: &str, options: &NewOptions) -> Result<Content> {
         let content_path = site.get_content_path()?;
         let file_path = Path::new(&content_path).join(&path);
 
         if file_path.exists() {
-            print!(
-                "File ({}) already exists. Type [Y] to overwrite it .[Y/N]",
-                path
-            );
-            io::stdout().flush().map_err(|err| {
-                Error::new("An error occurred while creating content.").with_inner_error(&err)
-            })?;
-            let mut buffer = String::new();
-            io::stdin().read_line(&mut buffer).map_err(|err| {
-                Error::new("An error occurred while creating content.").with_inner_error(&err)
-            })?;
-            if buffer.to_uppercase().starts_with("Y") {
-                fs::remove_file(&file_path).map_err(|err| {
-                    Error::new("An error occurred while creating content.").with_inner_error(&err)
-                })?;
-            } else {
-                return Err(Error::new("File already exists."));
+            let overwrite = match options.overwrite {
+                Overwrite::Force => true,
+                Overwrite::Never => false,
+                Overwrite::Ask if atty::is(atty::Stream::Stdin) => {
+                    let message = format!("File ({}) already exists. Type [Y] to overwrite it .[Y/N]", path);
+                    if !confirm(&message)? {
+                        return Err(Error::new("File already exists."));
+                    }
+                    true
+                }
+                Overwrite::Ask => {
+                    return Err(Error::new("File already exists. Use --force to overwrite it.")
+                        .with_path(&file_path.to_string_lossy()));
+                }
+            };
+            if !overwrite {
+                println!("{0:>12} {1} already exists", "Skipped".yellow().bold(), path);
+                return Self::load(site, path);
             }
+            fs::remove_file(&file_path).map_err(|err| {
+                Error::new("An error occurred while creating content.").with_source(err)
+            })?;
         }
+        println!(
+            "{0:>12} {1} {2}",
+            "Creating".green().bold(),
+            "content",
+            path
+        );
         let parent_path = file_path.parent().ok_or(Error::new(
             "An error occurred while getting parent directory from path.",
         ))?;
@@ -101,79 +206,726 @@ impl Content {
                 .create(parent_path)
                 .map_err(|err| {
                     Error::new("An error occurred while creating parent directory.")
-                        .with_inner_error(&err)
+                        .with_source(err)
                 })?;
         }
 
+        let data = Self::create_text(site, path, options)?;
         let mut file = fs::File::create(&file_path).map_err(|err| {
-            Error::new("An error occurred while creating file.").with_inner_error(&err)
+            Error::new("An error occurred while creating file.").with_source(err)
         })?;
-        let mut content = Content::default();
-        content.path = path.to_string();
-        let mut value = serde_json::to_value(content.clone()).unwrap();
-        let map = value.as_object_mut().unwrap();
-        map.remove("content").unwrap();
-        let mark = serde_json::to_string_pretty(&map)
-            .map_err(|err| Error::new("An error occurred while save file.").with_inner_error(&err))?
-            .to_string();
-        let data = format!("``````` json\r\n{}\r\n```````\r\n{}", mark, content.content);
         file.write_all(&mut data.into_bytes()).map_err(|err| {
-            Error::new("An error occurred while save file.").with_inner_error(&err)
+            Error::new("An error occurred while save file.").with_source(err)
         })?;
 
-        return Ok(content);
+        return Self::load(site, path);
     }
 
-    pub fn list(site: &Site) -> Result<()> {
-        let mut contents = Self::load_all(site)?;
-        contents.sort_by(|a, b| b.create_time.cmp(&a.create_time));
-        let mut table = Table::new();
-        table.set_titles(row!["TITLE", "CREATE DATE", "ID"]);
-        for item in &contents {
-            table.add_row(row![
-                item.title,
-                item.create_time.naive_local().format("%Y-%m-%d %H:%M:%S"),
-                item.id
-            ]);
-        }
-        table.set_format(*format::consts::FORMAT_CLEAN);
-        table.printstd();
+    /// Get the text of a new content.
+    ///
+    /// `archetypes/<kind>.md` is used if it exists, then `archetypes/default.md`, then the
+    /// built-in default. The archetype is a handlebars template, see `README.md` for the
+    /// placeholders. The `id`, `kind` and `create_time` missing in the archetype are added.
+    fn create_text(site: &Site, path: &str, options: &NewOptions) -> Result<String> {
+        let kind = options.kind.as_str();
+        let id = Uuid::new_v4();
+        let now = options
+            .date
+            .unwrap_or(Utc::now())
+            .to_rfc3339_opts(SecondsFormat::Secs, true);
+        let title = options.title.clone().unwrap_or(title_from_path(path));
+        let archetype_path = site.get_archetype_path()?;
+        let archetype = [kind, "default"]
+            .iter()
+            .map(|name| Path::new(&archetype_path).join(format!("{}.md", name)))
+            .find(|file| file.is_file());
+        let (text, file_name) = match archetype {
+            Some(file) => {
+                let file_name = file.to_string_lossy().to_string();
+                let mut buffer = String::new();
+                fs::File::open(&file)
+                    .and_then(|mut file| file.read_to_string(&mut buffer))
+                    .map_err(|err| {
+                        Error::new("Failed to read the archetype.").with_path(&file_name).with_source(err)
+                    })?;
+                let mut render = Handlebars::new();
+                render.register_escape_fn(no_escape);
+                render.register_helper("json", Box::new(json_helper));
+                let data = json!({
+                    "id": id.to_string(),
+                    "title": title,
+                    "date": now,
+                    "author": site.author,
+                    "kind": kind,
+                    "path": path,
+                    "site": site
+                });
+                let text = render.render_template(&buffer, &data).map_err(|err| {
+                    Error::new("Failed to render the archetype.")
+                        .with_kind(ErrorKind::Template)
+                        .with_path(&file_name)
+                        .with_source(err)
+                })?;
+                (text, file_name)
+            }
+            None => {
+                let mut content = Content::default();
+                content.id = id;
+                content.kind = kind.to_string();
+                let mut value = serde_json::to_value(content.clone()).unwrap();
+                {
+                    let map = value.as_object_mut().unwrap();
+                    map.remove("content").unwrap();
+                    map.remove("html").unwrap();
+                    map.remove("toc").unwrap();
+                }
+                (Self::format_text(&value, &content.content)?, path.to_string())
+            }
+        };
+        let (_, mark, _) = Self::split(&text)?.ok_or(
+            Error::new("Failed to find mark info on the archetype.")
+                .with_kind(ErrorKind::FrontMatter)
+                .with_path(&file_name),
+        )?;
+        let value = serde_json::from_str::<Value>(mark).map_err(|err| {
+            Error::new("Failed to convert mark info on the archetype.")
+                .with_kind(ErrorKind::FrontMatter)
+                .with_path(&file_name)
+                .with_source(err)
+        })?;
+        let mut replaced = mark.to_string();
+        let defaults = [("id", json!(id.to_string())), ("kind", json!(kind)), ("create_time", json!(now))];
+        for &(key, ref default) in defaults.iter() {
+            if value.get(key).is_none() {
+                replaced = set_mark_value(&replaced, key, default).map_err(|err| err.with_path(&file_name))?;
+            }
+        }
+        // The options win over the archetype.
+        let mut values = vec![];
+        if let Some(ref title) = options.title {
+            values.push(("title", json!(title)));
+        }
+        if let Some(ref tags) = options.tags {
+            values.push(("tags", json!(tags)));
+        }
+        if options.date.is_some() {
+            values.push(("create_time", json!(now)));
+        }
+        if options.draft {
+            values.push(("target", json!("DRAFT")));
+        }
+        for (key, value) in values {
+            replaced = set_mark_value(&replaced, key, &value).map_err(|err| err.with_path(&file_name))?;
+        }
+        let start = mark.as_ptr() as usize - text.as_ptr() as usize;
+        return Ok(format!("{}{}{}", &text[..start], replaced, &text[start + mark.len()..]));
+    }
+
+    /// Get the text of a content file from the mark info and the markdown.
+    pub fn format_text(mark: &Value, body: &str) -> Result<String> {
+        let mark = serde_json::to_string_pretty(mark)
+            .map_err(|err| Error::new("An error occurred while save file.").with_source(err))?;
+        return Ok(format!("``````` json\r\n{}\r\n```````\r\n{}", mark, body));
+    }
+
+    /// Convert the markdown to `html` and `toc`, so that the templates need not do it again.
+    pub fn render(&mut self) {
+        let (html, toc) = render_markdown(&self.content);
+        self.html = html;
+        self.toc = toc;
+    }
+
+    /// Parse a date like `2018-01-01` or a RFC 3339 date time like `2018-01-01T08:00:00+08:00`.
+    pub fn parse_date(text: &str) -> Result<DateTime<Utc>> {
+        if let Ok(date) = text.parse::<DateTime<Utc>>() {
+            return Ok(date);
+        }
+        let error = Error::new(&format!(
+            "Failed to parse the date \"{}\", expected a date like \"2018-01-01\".",
+            text
+        ));
+        let date = NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .map_err(|err| error.clone().with_source(err))?;
+        return date
+            .and_hms_opt(0, 0, 0)
+            .map(|date| Utc.from_utc_datetime(&date))
+            .ok_or(error);
+    }
+
+    /// Get the value of a column of `ims content list`.
+    fn column(&self, name: &str) -> String {
+        return match name {
+            "id" => self.id.to_string(),
+            "title" => self.title.clone(),
+            "description" => self.description.clone(),
+            "target" => self.target.clone(),
+            "kind" => self.kind.clone(),
+            "tags" => self.tags.join(","),
+            "date" => self.create_time.naive_local().format("%Y-%m-%d %H:%M:%S").to_string(),
+            "path" => self.path.clone(),
+            _ => String::new(),
+        };
+    }
+
+    /// Whether the content passes the filters of the options.
+    fn is_listed(&self, options: &ListOptions) -> bool {
+        if let Some(ref target) = options.target {
+            if !self.target.eq_ignore_ascii_case(target) {
+                return false;
+            }
+        }
+        if !options.tags.iter().all(|tag| self.tags.contains(tag)) {
+            return false;
+        }
+        if let Some(since) = options.since {
+            if self.create_time < since {
+                return false;
+            }
+        }
+        if let Some(ref search) = options.search {
+            let search = search.to_lowercase();
+            let found = [&self.title, &self.description, &self.tags.join(" "), &self.content]
+                .iter()
+                .any(|text| text.to_lowercase().contains(&search));
+            if !found {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /// List the contents, drafts included.
+    pub fn list(site: &Site, options: &ListOptions) -> Result<()> {
+        let mut contents = vec![];
+        for (path, result) in Self::load_every(site)? {
+            match result {
+                Ok(content) => contents.push(content),
+                Err(err) => warn!("Failed to load content:{}. error:{}", path, err),
+            }
+        }
+        contents.retain(|content| content.is_listed(options));
+        match options.sort.as_ref() {
+            "title" => contents.sort_by(|a, b| a.title.cmp(&b.title)),
+            "path" => contents.sort_by(|a, b| a.path.cmp(&b.path)),
+            "target" => contents.sort_by(|a, b| a.target.cmp(&b.target)),
+            "id" => contents.sort_by(|a, b| a.id.cmp(&b.id)),
+            _ => contents.sort_by(|a, b| b.create_time.cmp(&a.create_time)),
+        }
+        if options.reverse {
+            contents.reverse();
+        }
+        let rows: Vec<Vec<String>> = contents
+            .iter()
+            .map(|content| options.columns.iter().map(|x| content.column(x)).collect())
+            .collect();
+        match options.format.as_ref() {
+            "json" => {
+                let list: Vec<Value> = contents
+                    .iter()
+                    .map(|content| {
+                        let mut map = serde_json::Map::new();
+                        for name in &options.columns {
+                            let value = match name.as_ref() {
+                                "tags" => json!(content.tags),
+                                "date" => json!(content.create_time),
+                                _ => json!(content.column(name)),
+                            };
+                            map.insert(name.clone(), value);
+                        }
+                        Value::Object(map)
+                    })
+                    .collect();
+                let data = serde_json::to_string_pretty(&list).map_err(|err| {
+                    Error::new("Failed to convert the contents.").with_source(err)
+                })?;
+                println!("{}", data);
+            }
+            "csv" => {
+                let escape = |field: &str| {
+                    if field.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+                        format!("\"{}\"", field.replace('"', "\"\""))
+                    } else {
+                        field.to_string()
+                    }
+                };
+                println!("{}", options.columns.join(","));
+                for row in &rows {
+                    let fields: Vec<String> = row.iter().map(|x| escape(x)).collect();
+                    println!("{}", fields.join(","));
+                }
+            }
+            _ => {
+                let mut table = Table::new();
+                let titles = options
+                    .columns
+                    .iter()
+                    .map(|x| match x.as_ref() {
+                        "date" => Cell::new("CREATE DATE"),
+                        _ => Cell::new(&x.to_uppercase()),
+                    })
+                    .collect();
+                table.set_titles(Row::new(titles));
+                for row in &rows {
+                    table.add_row(Row::new(row.iter().map(|x| Cell::new(x)).collect()));
+                }
+                table.set_format(*format::consts::FORMAT_CLEAN);
+                table.printstd();
+            }
+        }
         return Ok(());
     }
 
-    /// Load Content from exists file.
-    pub fn load(site: &Site, path: &str) -> Result<Content> {
-        trace!("Loading content {}", path);
+    /// Read the file of a content, get the full path and the text.
+    fn read(site: &Site, path: &str) -> Result<(String, String)> {
         let content_path = site.get_content_path()?;
         let file_path = Path::new(&content_path).join(path);
+        let file_name = file_path.to_string_lossy().to_string();
         if !file_path.exists() {
-            return Err(Error::new("The file is not exists."));
+            return Err(Error::new("The file is not exists.")
+                .with_kind(ErrorKind::Io)
+                .with_path(&file_name));
         }
         let mut file = fs::File::open(file_path)
-            .map_err(|err| Error::new("Failed to open the file.").with_inner_error(&err))?;
+            .map_err(|err| {
+                Error::new("Failed to open the file.").with_path(&file_name).with_source(err)
+            })?;
         let mut buffer = String::new();
         file.read_to_string(&mut buffer)
-            .map_err(|err| Error::new("Failed to read file.").with_inner_error(&err))?;
+            .map_err(|err| {
+                Error::new("Failed to read file.").with_path(&file_name).with_source(err)
+            })?;
+        return Ok((file_name, buffer));
+    }
 
+    /// Split the text of a content into the mark info and the markdown.
+    ///
+    /// The number of lines before the opening fence is also returned, the mark starts on
+    /// the line of the opening fence.
+    fn split(buffer: &str) -> Result<Option<(usize, &str, &str)>> {
         let re = Regex::new(r"^\s*``````` json(?P<mark>(.|\s)*?)```````(?P<content>(.|\s)*)")
             .map_err(|err| {
-                Error::new("An error occurred while resolving the content.").with_inner_error(&err)
+                Error::new("An error occurred while resolving the content.").with_source(err)
             })?;
+        let caps = match re.captures(buffer) {
+            Some(caps) => caps,
+            None => return Ok(None),
+        };
+        let mark = caps.name("mark").unwrap();
+        let content = caps.name("content").unwrap();
+        let offset = buffer[..mark.start()].matches('\n').count();
+        return Ok(Some((offset, mark.as_str(), content.as_str())));
+    }
 
-        let caps = re.captures(&buffer)
-            .ok_or(Error::new("Failed to find mark info on the content."))?;
+    /// Load Content from exists file.
+    pub fn load(site: &Site, path: &str) -> Result<Content> {
+        trace!("Loading content {}", path);
+        let (file_name, buffer) = Self::read(site, path)?;
+        let (offset, mark, body) = Self::split(&buffer)?.ok_or(
+            Error::new("Failed to find mark info on the content.")
+                .with_kind(ErrorKind::FrontMatter)
+                .with_path(&file_name),
+        )?;
 
-        let mut content = serde_json::from_str::<Content>(&caps["mark"]).map_err(|error| {
-            Error::new("Failed to convert mark info on the content.").with_inner_error(&error)
+        let mut content = serde_json::from_str::<Content>(mark).map_err(|error| {
+            let (line, column) = (offset + error.line(), error.column());
+            Error::new("Failed to convert mark info on the content.")
+                .with_kind(ErrorKind::FrontMatter)
+                .with_path(&file_name)
+                .with_position(line, column)
+                .with_source(error)
         })?;
 
-        content.content = caps["content"].to_string();
+        content.content = body.to_string();
         content.path = path.to_string();
         return Ok(content);
     }
 
-    pub fn load_all(site: &Site) -> Result<Vec<Content>> {
-        trace!("Loading contents");
+    /// Get the mark info and the first lines of the markdown, as they are written in the file.
+    pub fn preview(site: &Site, path: &str, lines: usize) -> Result<String> {
+        let (_, buffer) = Self::read(site, path)?;
+        let (mark, body) = match Self::split(&buffer)? {
+            Some((_, mark, body)) => (mark, body),
+            None => ("", buffer.as_str()),
+        };
+        let body: Vec<&str> = body
+            .lines()
+            .map(|x| x.trim_end())
+            .skip_while(|x| x.is_empty())
+            .take(lines)
+            .collect();
+        return Ok(format!("{}\n\n{}", mark.trim(), body.join("\n")));
+    }
+
+    /// Check the mark info of a content against the schema of `Content`.
+    pub fn validate(site: &Site, path: &str) -> Result<Vec<Diagnostic>> {
+        let (file_name, buffer) = Self::read(site, path)?;
+        let (offset, mark, body) = match Self::split(&buffer)? {
+            Some(parts) => parts,
+            None => {
+                return Ok(vec![Diagnostic::error(
+                    &file_name,
+                    "Failed to find mark info on the content.",
+                )]);
+            }
+        };
+        let value = match serde_json::from_str::<Value>(mark) {
+            Ok(value) => value,
+            Err(error) => {
+                let message = format!("The mark info is not valid json. {}", error);
+                return Ok(vec![
+                    Diagnostic::error(&file_name, &message).with_line(Some(offset + error.line())),
+                ]);
+            }
+        };
+        let map = match value.as_object() {
+            Some(map) => map,
+            None => {
+                return Ok(vec![Diagnostic::error(&file_name, "The mark info must be an object.")
+                    .with_line(Some(offset + 1))]);
+            }
+        };
+        // Line of the key in the file, the last one wins as in the parsed map.
+        let offsets = mark_key_offsets(mark);
+        let line_of = |key: &str| -> Option<usize> {
+            offsets
+                .iter()
+                .rev()
+                .find(|x| x.0 == key)
+                .map(|x| offset + 1 + mark[..x.1].matches('\n').count())
+        };
+        let mut diagnostics = vec![];
+        for (key, value) in map.iter() {
+            let error = |message: &str| {
+                Diagnostic::error(&file_name, message)
+                    .with_line(line_of(key))
+                    .with_key(key)
+            };
+            let warning = |message: &str| {
+                Diagnostic::warning(&file_name, message)
+                    .with_line(line_of(key))
+                    .with_key(key)
+            };
+            match key.as_ref() {
+                "id" => match value.as_str().map(|x| x.parse::<Uuid>()) {
+                    Some(Ok(_)) => {}
+                    _ => diagnostics.push(error(&format!("Expected a UUID string, found {}.", value))),
+                },
+                "title" | "description" | "target" | "kind" => match value.as_str() {
+                    Some(text) => {
+                        if key == "title" && text == default_title() {
+                            diagnostics.push(warning("The default placeholder is not replaced."));
+                        }
+                        if key == "description" && text == default_description() {
+                            diagnostics.push(warning("The default placeholder is not replaced."));
+                        }
+                        if key == "target" && text != "POST" && text != "DRAFT" {
+                            diagnostics.push(warning(&format!(
+                                "Expected \"POST\" or \"DRAFT\", found {}. Only \"POST\" is published.",
+                                value
+                            )));
+                        }
+                    }
+                    None => diagnostics.push(error(&format!("Expected a string, found {}.", value))),
+                },
+                "tags" | "aliases" => {
+                    let valid = value
+                        .as_array()
+                        .map(|list| list.iter().all(|x| x.is_string()))
+                        .unwrap_or(false);
+                    if !valid {
+                        diagnostics.push(error(&format!(
+                            "Expected an array of strings, found {}.",
+                            value
+                        )));
+                    }
+                }
+                "create_time" => match value.as_str().map(|x| x.parse::<DateTime<Utc>>()) {
+                    Some(Ok(_)) => {}
+                    _ => diagnostics.push(error(&format!(
+                        "Expected a RFC 3339 date time like \"2018-01-01T00:00:00Z\", found {}.",
+                        value
+                    ))),
+                },
+                "meta" => {}
+                _ => diagnostics.push(warning("Unknown key, it is ignored.")),
+            }
+        }
+        if !map.contains_key("id") {
+            diagnostics.push(
+                Diagnostic::warning(&file_name, "A new id is generated on every load.")
+                    .with_line(Some(offset + 1))
+                    .with_key("id"),
+            );
+        }
+        if !map.contains_key("create_time") {
+            diagnostics.push(
+                Diagnostic::warning(&file_name, "The current time is used on every load.")
+                    .with_line(Some(offset + 1))
+                    .with_key("create_time"),
+            );
+        }
+        if body.trim() == default_content().trim() {
+            diagnostics.push(Diagnostic::warning(
+                &file_name,
+                "The default placeholder of the markdown is not replaced.",
+            ));
+        }
+        diagnostics.sort_by_key(|x| x.line);
+        return Ok(diagnostics);
+    }
+
+    /// Check all contents, see `validate` and `find_duplicates`.
+    pub fn check_all(site: &Site) -> Result<Vec<Diagnostic>> {
+        let paths = Self::get_all_path(site)?;
+        let results: Vec<Result<Vec<Diagnostic>>> =
+            paths.par_iter().map(|path| Self::validate(site, path)).collect();
+        let mut diagnostics = vec![];
+        for result in results {
+            diagnostics.extend(result?);
+        }
+        let contents: Vec<Content> = Self::load_every(site)?
+            .into_iter()
+            .filter_map(|(_, result)| result.ok())
+            .collect();
+        diagnostics.extend(Self::find_duplicates(site, &contents)?);
+        return Ok(diagnostics);
+    }
+
+    /// The `slug` in `meta`, themes may use it in the output paths.
+    pub fn slug(&self) -> Option<&str> {
+        return self.meta.get("slug").and_then(|x| x.as_str());
+    }
+
+    /// Group the contents sharing an id or a slug, only the groups with more than one content.
+    ///
+    /// The original of a group, i.e. the oldest content, is the first one.
+    fn duplicate_groups(contents: &[Content]) -> Vec<(&'static str, String, Vec<&Content>)> {
+        let mut groups: BTreeMap<(&'static str, String), Vec<&Content>> = BTreeMap::new();
+        for content in contents {
+            groups
+                .entry(("id", content.id.to_string()))
+                .or_insert(vec![])
+                .push(content);
+            if let Some(slug) = content.slug() {
+                groups
+                    .entry(("slug", slug.to_string()))
+                    .or_insert(vec![])
+                    .push(content);
+            }
+        }
+        return groups
+            .into_iter()
+            .filter(|&(_, ref list)| list.len() > 1)
+            .map(|((key, value), mut list)| {
+                list.sort_by(|a, b| (a.create_time, &a.path).cmp(&(b.create_time, &b.path)));
+                (key, value, list)
+            })
+            .collect();
+    }
+
+    /// Find the contents sharing an id or a slug, every colliding file gets an error.
+    pub fn find_duplicates(site: &Site, contents: &[Content]) -> Result<Vec<Diagnostic>> {
+        let content_path = site.get_content_path()?;
+        let mut diagnostics = vec![];
+        for (key, value, list) in Self::duplicate_groups(contents) {
+            for content in &list {
+                let file_name = Path::new(&content_path).join(&content.path);
+                let others: Vec<&str> = list
+                    .iter()
+                    .filter(|x| x.path != content.path)
+                    .map(|x| x.path.as_str())
+                    .collect();
+                let message = format!(
+                    "Duplicate {} \"{}\", also used by {}.",
+                    key,
+                    value,
+                    others.join(", ")
+                );
+                diagnostics.push(Diagnostic::error(&file_name.to_string_lossy(), &message).with_key(key));
+            }
+        }
+        return Ok(diagnostics);
+    }
+
+    /// Give a new id to the contents whose id is used by an older content.
+    pub fn fix_ids(site: &Site) -> Result<usize> {
+        let contents: Vec<Content> = Self::load_every(site)?
+            .into_iter()
+            .filter_map(|(_, result)| result.ok())
+            .collect();
+        let groups = Self::duplicate_groups(&contents);
+        let mut count = 0;
+        for &(key, ref value, ref list) in &groups {
+            if key != "id" {
+                continue;
+            }
+            for content in list.iter().skip(1) {
+                let id = Uuid::new_v4();
+                Self::update_mark(site, &content.path, &[("id", json!(id.to_string()))])?;
+                println!(
+                    "{0:>12} {1}: {2} -> {3}",
+                    "Fixed".green().bold(),
+                    content.path,
+                    value,
+                    id
+                );
+                count += 1;
+            }
+        }
+        for &(key, ref value, ref list) in &groups {
+            if key == "slug" {
+                let paths: Vec<&str> = list.iter().map(|x| x.path.as_str()).collect();
+                println!(
+                    "{0:>12} slug \"{1}\" is used by {2}, change it by hand.",
+                    "Warning".yellow().bold(),
+                    value,
+                    paths.join(", ")
+                );
+            }
+        }
+        return Ok(count);
+    }
+
+    /// Set the values in the mark info of a content, the rest of the file is kept as it is.
+    pub fn update_mark(site: &Site, path: &str, values: &[(&str, Value)]) -> Result<()> {
+        let (file_name, buffer) = Self::read(site, path)?;
+        let (_, mark, _) = Self::split(&buffer)?.ok_or(
+            Error::new("Failed to find mark info on the content.")
+                .with_kind(ErrorKind::FrontMatter)
+                .with_path(&file_name),
+        )?;
+        let start = mark.as_ptr() as usize - buffer.as_ptr() as usize;
+        let mut replaced = mark.to_string();
+        for &(key, ref value) in values {
+            replaced = set_mark_value(&replaced, key, value).map_err(|err| err.with_path(&file_name))?;
+        }
+        let data = format!("{}{}{}", &buffer[..start], replaced, &buffer[start + mark.len()..]);
+        fs::write(&file_name, data).map_err(|err| {
+            Error::new("An error occurred while save file.")
+                .with_path(&file_name)
+                .with_source(err)
+        })?;
+        return Ok(());
+    }
+
+    /// Find the path of a content by the path or the id, a unique prefix of the id is enough.
+    pub fn resolve(site: &Site, target: &str) -> Result<String> {
+        let content_path = site.get_content_path()?;
+        // A path outside the content directory is not a content, e.g. `../site.json`.
+        if let Some(path) = normalize_output_path(target) {
+            if Path::new(&content_path).join(&path).is_file() {
+                return Ok(path);
+            }
+        }
+        let prefix = target.to_lowercase();
+        let paths: Vec<String> = Self::load_every(site)?
+            .into_iter()
+            .filter_map(|(_, result)| result.ok())
+            .filter(|x| prefix.len() >= 4 && x.id.to_string().starts_with(&prefix))
+            .map(|x| x.path)
+            .collect();
+        return match paths.len() {
+            0 => Err(Error::new(&format!("No content matches \"{}\".", target))),
+            1 => Ok(paths[0].clone()),
+            _ => Err(Error::new(&format!(
+                "More than one content matches \"{}\": {}.",
+                target,
+                paths.join(", ")
+            ))),
+        };
+    }
+
+    /// Set the target of the content at the path to `POST` and the create time to now, or
+    /// set the target to `DRAFT`.
+    pub fn set_published(site: &Site, path: &str, published: bool) -> Result<()> {
+        if published {
+            let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+            return Self::update_mark(site, path, &[("target", json!("POST")), ("create_time", json!(now))]);
+        }
+        return Self::update_mark(site, path, &[("target", json!("DRAFT"))]);
+    }
+
+    /// Set the target of a content to `POST`, and the create time to now.
+    pub fn publish(site: &Site, target: &str) -> Result<()> {
+        let path = Self::resolve(site, target)?;
+        Self::set_published(site, &path, true)?;
+        println!("{0:>12} {1}", "Published".green().bold(), path);
+        return Ok(());
+    }
+
+    /// Set the target of a content to `DRAFT`.
+    pub fn unpublish(site: &Site, target: &str) -> Result<()> {
+        let path = Self::resolve(site, target)?;
+        Self::set_published(site, &path, false)?;
+        println!("{0:>12} {1}", "Unpublished".green().bold(), path);
+        return Ok(());
+    }
+
+    /// Move a content to another path, the id is kept and the old path is added to `aliases`.
+    pub fn rename(site: &Site, target: &str, new_path: &str) -> Result<()> {
+        let new_path = normalize_output_path(new_path).ok_or(
+            Error::new(&format!("The path \"{}\" is outside the content directory.", new_path))
+                .with_kind(ErrorKind::Config),
+        )?;
+        let path = Self::resolve(site, target)?;
+        let content = Self::load(site, &path)?;
+        let content_path = site.get_content_path()?;
+        let (source, target) = (
+            Path::new(&content_path).join(&path),
+            Path::new(&content_path).join(&new_path),
+        );
+        if target.exists() {
+            return Err(Error::new("File already exists.").with_path(&target.to_string_lossy()));
+        }
+        if let Some(parent_path) = target.parent() {
+            fs::DirBuilder::new()
+                .recursive(true)
+                .create(parent_path)
+                .map_err(|err| {
+                    Error::new("An error occurred while creating parent directory.")
+                        .with_source(err)
+                })?;
+        }
+        fs::rename(&source, &target).map_err(|err| {
+            Error::new("Failed to move the content.")
+                .with_path(&source.to_string_lossy())
+                .with_source(err)
+        })?;
+        let mut aliases = content.aliases.clone();
+        if !aliases.contains(&path) {
+            aliases.push(path.clone());
+        }
+        aliases.retain(|x| x != &new_path);
+        Self::update_mark(site, &new_path, &[("aliases", json!(aliases))])?;
+        println!("{0:>12} {1} -> {2}", "Moved".green().bold(), path, new_path);
+        return Ok(());
+    }
+
+    /// Delete a content, ask before deleting unless `yes`.
+    pub fn remove(site: &Site, target: &str, yes: bool) -> Result<()> {
+        let path = Self::resolve(site, target)?;
+        let message = format!("Remove the content ({}) ? Type [Y] to remove it .[Y/N]", path);
+        if !yes && !confirm(&message)? {
+            return Err(Error::new("The content is not removed."));
+        }
+        Self::delete(site, &path)?;
+        println!("{0:>12} {1}", "Removed".green().bold(), path);
+        return Ok(());
+    }
+
+    /// Delete the file of the content at the path without asking.
+    pub fn delete(site: &Site, path: &str) -> Result<()> {
+        let file_path = Path::new(&site.get_content_path()?).join(path);
+        fs::remove_file(&file_path).map_err(|err| {
+            Error::new("Failed to remove the content.")
+                .with_path(&file_path.to_string_lossy())
+                .with_source(err)
+        })?;
+        return Ok(());
+    }
+
+    /// Get the paths of all content files relative to the content directory, sorted.
+    pub fn get_all_path(site: &Site) -> Result<Vec<String>> {
         let content_path = site.get_content_path()?;
         let parent_path = Path::new(&content_path);
         let list = get_all_file(&parent_path)?;
@@ -186,7 +938,7 @@ impl Content {
                 .map_err(|err| {
                     Error::new(&format!(
                         "The Path is not The child path of the parent path."
-                    )).with_inner_error(&err)
+                    )).with_source(err)
                 })?;
             paths.push(
                 path.to_str()
@@ -194,20 +946,215 @@ impl Content {
                     .to_string(),
             );
         }
-        let mut contents = vec![];
-        for path in paths {
-            let content = match Self::load(&site, &path) {
-                Ok(content) => content,
+        paths.sort();
+        return Ok(paths);
+    }
+
+    /// Load all content files whatever the target is, sorted by path.
+    pub fn load_every(site: &Site) -> Result<Vec<(String, Result<Content>)>> {
+        // Sorted so that the result does not depend on the order of the parallel loading.
+        let paths = Self::get_all_path(site)?;
+        let results: Vec<Result<Content>> =
+            paths.par_iter().map(|path| Self::load(&site, path)).collect();
+        return Ok(paths.into_iter().zip(results).collect());
+    }
+
+    pub fn load_all(site: &Site) -> Result<Vec<Content>> {
+        return Self::load_all_into(site, &mut BuildReport::new());
+    }
+
+    /// Load all published contents, the skipped and failed files are added to the report.
+    pub fn load_all_into(site: &Site, report: &mut BuildReport) -> Result<Vec<Content>> {
+        trace!("Loading contents");
+        let mut loaded = vec![];
+        for (path, result) in Self::load_every(site)? {
+            match result {
+                Ok(content) => loaded.push(content),
                 Err(err) => {
                     warn!("Failed to load content:{}. error:{}", path, err);
-                    continue;
+                    report.failed.push(ReportItem::from_error(&path, &err));
                 }
-            };
+            }
+        }
+        let mut posts = vec![];
+        for content in loaded {
             if content.target == "POST" {
-                contents.push(content);
+                posts.push(content);
+            } else {
+                report.drafts += 1;
+                let reason = format!("The target is \"{}\".", content.target);
+                report.skipped.push(ReportItem::new(&content.path, &reason));
             }
         }
+        // Only the original of the duplicates is kept, otherwise they overwrite each other.
+        // The drafts are not rendered, so they never hide a published content.
+        let mut duplicates = BTreeMap::new();
+        for (key, value, list) in Self::duplicate_groups(&posts) {
+            for content in list.iter().skip(1) {
+                let reason = format!(
+                    "Duplicate {} \"{}\", {} is kept. Run `ims content fix-ids` to fix the ids.",
+                    key, value, list[0].path
+                );
+                duplicates.entry(content.path.clone()).or_insert(reason);
+            }
+        }
+        for (path, reason) in duplicates.iter() {
+            warn!("Skipped content:{}. {}", path, reason);
+            report.failed.push(ReportItem::new(path, reason));
+        }
+        let contents: Vec<Content> = posts
+            .into_iter()
+            .filter(|content| !duplicates.contains_key(&content.path))
+            .collect();
+        report.posts = contents.len();
         trace!("Loaded {} content(s)", contents.len());
         return Ok(contents);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    /// A site in a new temporary directory, with the contents as (path, mark info).
+    fn create_site(name: &str, contents: &[(&str, &str)]) -> Site {
+        let root = env::temp_dir().join(format!("ims-test-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("content")).unwrap();
+        for &(path, mark) in contents {
+            let text = format!("``````` json\n{}\n```````\n# {}\n", mark, path);
+            fs::write(root.join("content").join(path), text).unwrap();
+        }
+        let mut site = serde_json::from_str::<Site>("{}").unwrap();
+        site.root = root.to_string_lossy().to_string();
+        return site;
+    }
+
+    #[test]
+    fn load_all_into_keeps_the_post_sharing_an_id_with_an_older_draft() {
+        let id = "00000000-0000-0000-0000-000000000001";
+        let site = create_site(
+            "draft-duplicate",
+            &[
+                ("draft.md", &format!(r#"{{"id":"{}","target":"DRAFT","create_time":"2018-01-01T00:00:00Z"}}"#, id)),
+                ("post.md", &format!(r#"{{"id":"{}","target":"POST","create_time":"2018-02-01T00:00:00Z"}}"#, id)),
+            ],
+        );
+        let mut report = BuildReport::new();
+        let contents = Content::load_all_into(&site, &mut report).unwrap();
+        fs::remove_dir_all(&site.root).unwrap();
+        let paths: Vec<&str> = contents.iter().map(|x| x.path.as_str()).collect();
+        assert_eq!(paths, vec!["post.md"]);
+        assert!(report.failed.is_empty());
+        assert_eq!(report.drafts, 1);
+    }
+
+    #[test]
+    fn load_all_into_keeps_the_oldest_of_duplicate_posts() {
+        let id = "00000000-0000-0000-0000-000000000002";
+        let site = create_site(
+            "post-duplicate",
+            &[
+                ("new.md", &format!(r#"{{"id":"{}","target":"POST","create_time":"2018-02-01T00:00:00Z"}}"#, id)),
+                ("old.md", &format!(r#"{{"id":"{}","target":"POST","create_time":"2018-01-01T00:00:00Z"}}"#, id)),
+            ],
+        );
+        let mut report = BuildReport::new();
+        let contents = Content::load_all_into(&site, &mut report).unwrap();
+        fs::remove_dir_all(&site.root).unwrap();
+        let paths: Vec<&str> = contents.iter().map(|x| x.path.as_str()).collect();
+        assert_eq!(paths, vec!["old.md"]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].path, "new.md");
+    }
+
+    #[test]
+    fn publish_unpublish_and_rename_keep_the_rest_of_the_file() {
+        let site = create_site("edit-mark", &[]);
+        let content_path = Path::new(&site.root).join("content");
+        let mark = concat!(
+            "{\n",
+            "  \"id\": \"00000000-0000-0000-0000-000000000003\",\n",
+            "  \"title\": \"A, \\\"quoted\\\" }\",\n",
+            "  \"target\": \"DRAFT\",\n",
+            "  \"create_time\": \"2018-01-01T00:00:00Z\",\n",
+            "  \"meta\": {\"target\": \"x\", \"custom\": [1, 2]}\n",
+            "}"
+        );
+        let body = "\n# 静态博客\n\n```\n{\"target\": \"DRAFT\"}\n```\r\nend \n";
+        let original = format!("``````` json\n{}\n```````{}", mark, body);
+        fs::write(content_path.join("a.md"), &original).unwrap();
+
+        Content::publish(&site, "a.md").unwrap();
+        let text = fs::read_to_string(content_path.join("a.md")).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[4], "  \"target\": \"POST\",");
+        assert!(lines[5].starts_with("  \"create_time\": \""));
+        assert!(!lines[5].contains("2018-01-01"));
+
+        Content::unpublish(&site, "a.md").unwrap();
+        let text = fs::read_to_string(content_path.join("a.md")).unwrap();
+        let create_time = text.lines().nth(5).unwrap().to_string();
+        assert_eq!(text, original.replace("  \"create_time\": \"2018-01-01T00:00:00Z\",", &create_time));
+
+        Content::rename(&site, "a.md", "./posts//b.md").unwrap();
+        assert!(!content_path.join("a.md").exists());
+        let text = fs::read_to_string(content_path.join("posts/b.md")).unwrap();
+        fs::remove_dir_all(&site.root).unwrap();
+        let tail = "  \"meta\": {\"target\": \"x\", \"custom\": [1, 2]},\n  \"aliases\": [\"a.md\"]\n}\n```````";
+        assert!(text.ends_with(&format!("{}{}", tail, body)));
+    }
+
+    #[test]
+    fn rename_keeps_the_content_in_the_content_directory() {
+        let site = create_site("rename-outside", &[("a.md", r#"{"title":"A"}"#)]);
+        let content_path = Path::new(&site.root).join("content");
+        for path in &["../a.md", "x/../../a.md", "../../etc/a.md", "C:/a.md"] {
+            let err = Content::rename(&site, "a.md", path).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::Config, "{}", path);
+        }
+        assert!(Content::publish(&site, "../content/a.md").is_err());
+        // An absolute path is relative to the content directory.
+        Content::rename(&site, "a.md", "/b.md").unwrap();
+        let moved = content_path.join("b.md").is_file();
+        fs::remove_dir_all(&site.root).unwrap();
+        assert!(moved);
+    }
+    #[test]
+    fn validate_reports_the_lines_of_the_top_level_keys() {
+        let mark = concat!(
+            "{\n",
+            "  \"meta\": {\"tags\": \"x\", \"create_time\": 1, \"colour\": 2},\n",
+            "  \"title\": \"TITLE\",\n",
+            "  \"tags\": \"a, b\",\n",
+            "  \"create_time\": \"yesterday\",\n",
+            "  \"colour\": \"red\",\n",
+            "  \"target\": \"POST\"\n",
+            "}",
+        );
+        let site = create_site("validate", &[("a.md", mark)]);
+        let diagnostics = Content::validate(&site, "a.md").unwrap();
+        fs::remove_dir_all(&site.root).unwrap();
+        let found: Vec<(Option<usize>, &str, bool, &str)> = diagnostics
+            .iter()
+            .map(|x| (x.line, x.key.as_ref().map_or("", |x| x.as_str()), x.is_error(), x.message.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (Some(1), "id", false, "A new id is generated on every load."),
+                (Some(4), "title", false, "The default placeholder is not replaced."),
+                (Some(5), "tags", true, "Expected an array of strings, found \"a, b\"."),
+                (
+                    Some(6),
+                    "create_time",
+                    true,
+                    "Expected a RFC 3339 date time like \"2018-01-01T00:00:00Z\", found \"yesterday\".",
+                ),
+                (Some(7), "colour", false, "Unknown key, it is ignored."),
+            ]
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use colored::*;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found by `ims check`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub path: String,
+    pub line: Option<usize>,
+    /// The front matter key the problem is about.
+    pub key: Option<String>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(path: &str, message: &str) -> Diagnostic {
+        return Diagnostic {
+            severity: Severity::Error,
+            path: path.to_string(),
+            line: None,
+            key: None,
+            message: message.to_string(),
+        };
+    }
+
+    pub fn warning(path: &str, message: &str) -> Diagnostic {
+        let mut diagnostic = Diagnostic::error(path, message);
+        diagnostic.severity = Severity::Warning;
+        return diagnostic;
+    }
+
+    pub fn with_line(mut self, line: Option<usize>) -> Diagnostic {
+        self.line = line;
+        return self;
+    }
+
+    pub fn with_key(mut self, key: &str) -> Diagnostic {
+        self.key = Some(key.to_string());
+        return self;
+    }
+
+    pub fn is_error(&self) -> bool {
+        return self.severity == Severity::Error;
+    }
+
+    pub fn print(&self) {
+        let title = match self.severity {
+            Severity::Error => "Error".red().bold(),
+            Severity::Warning => "Warning".yellow().bold(),
+        };
+        let mut location = self.path.clone();
+        if let Some(line) = self.line {
+            location = format!("{}:{}", location, line);
+        }
+        match self.key {
+            Some(ref key) => println!("{0:>12} {1}: \"{2}\": {3}", title, location, key, self.message),
+            None => println!("{0:>12} {1}: {2}", title, location, self.message),
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use std;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+use colored::*;
+use serde_json::{self, Map, Value};
+use serde_yaml;
+use toml;
+
+use super::super::infrastructure::*;
+use super::{Content, Diagnostic, Site};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// The formats of `ims export`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Hugo,
+    Jekyll,
+    /// The render model of the templates in a single file.
+    Json,
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<ExportFormat> {
+        return match name {
+            "hugo" => Some(ExportFormat::Hugo),
+            "jekyll" => Some(ExportFormat::Jekyll),
+            "json" => Some(ExportFormat::Json),
+            _ => None,
+        };
+    }
+}
+
+/// Remove the null values, TOML has no null.
+fn remove_nulls(value: Value) -> Value {
+    return match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|&(_, ref value)| !value.is_null())
+                .map(|(key, value)| (key, remove_nulls(value)))
+                .collect(),
+        ),
+        Value::Array(list) => Value::Array(list.into_iter().filter(|x| !x.is_null()).map(remove_nulls).collect()),
+        value => value,
+    };
+}
+
+/// Writes the contents of a site in the format of another generator.
+pub struct Exporter<'a> {
+    site: &'a Site,
+    directory: PathBuf,
+    /// Paths of the files written, relative to the directory.
+    written: BTreeSet<String>,
+    pub exported: usize,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Exporter<'a> {
+    /// The directory must be empty unless `force`.
+    pub fn new(site: &'a Site, directory: &Path, force: bool) -> Result<Exporter<'a>> {
+        let is_empty = fs::read_dir(directory)
+            .map(|mut x| x.next().is_none())
+            .unwrap_or(true);
+        if !is_empty && !force {
+            return Err(Error::new("The directory is not empty, use --force to write into it.")
+                .with_path(&directory.to_string_lossy()));
+        }
+        return Ok(Exporter {
+            site: site,
+            directory: directory.to_path_buf(),
+            written: BTreeSet::new(),
+            exported: 0,
+            diagnostics: vec![],
+        });
+    }
+
+    /// Write a file, the path is relative to the directory.
+    fn write(&self, path: &str, text: &str) -> Result<()> {
+        let file_path = self.directory.join(path);
+        if let Some(parent_path) = file_path.parent() {
+            fs::DirBuilder::new()
+                .recursive(true)
+                .create(parent_path)
+                .map_err(|err| {
+                    Error::new("An error occurred while creating parent directory.").with_source(err)
+                })?;
+        }
+        fs::write(&file_path, text).map_err(|err| {
+            Error::new("An error occurred while save file.")
+                .with_path(&file_path.to_string_lossy())
+                .with_source(err)
+        })?;
+        return Ok(());
+    }
+
+    pub fn export(&mut self, format: ExportFormat) -> Result<()> {
+        if format == ExportFormat::Json {
+            let model = self.site.render_model()?;
+            let text = serde_json::to_string_pretty(&model)
+                .map_err(|err| Error::new("Failed to convert the render model.").with_source(err))?;
+            self.write("model.json", &text)?;
+            self.exported = model["contents"].as_array().map_or(0, |x| x.len());
+            println!("{0:>12} model.json", "Exported".green().bold());
+            return Ok(());
+        }
+        for (path, result) in Content::load_every(self.site)? {
+            match result.and_then(|content| self.export_content(format, &content)) {
+                Ok(target) => {
+                    println!("{0:>12} {1} -> {2}", "Converted".green().bold(), path, target);
+                    self.exported += 1;
+                }
+                Err(err) => self.diagnostics.push(Diagnostic::error(&path, &err.reason())),
+            }
+        }
+        // The images of the contents are in the static directory.
+        let static_path = self.site.get_static_path()?;
+        let static_path = Path::new(&static_path);
+        if static_path.is_dir() {
+            let target = match format {
+                ExportFormat::Hugo => self.directory.join("static"),
+                _ => self.directory.clone(),
+            };
+            copy_all_file(static_path, &target, |_, _| true)?;
+        }
+        return Ok(());
+    }
+
+    /// Get the front matter of the content, the keys of `meta` are moved to the top level.
+    fn front_matter(&self, format: ExportFormat, content: &Content) -> Value {
+        let mut map = Map::new();
+        if let Value::Object(ref meta) = content.meta {
+            for (key, value) in meta {
+                map.insert(key.clone(), value.clone());
+            }
+        }
+        map.insert("id".to_string(), json!(content.id.to_string()));
+        map.insert("title".to_string(), json!(content.title));
+        map.insert("tags".to_string(), json!(content.tags));
+        if !content.description.is_empty() {
+            map.insert("description".to_string(), json!(content.description));
+        }
+        match format {
+            ExportFormat::Hugo => {
+                map.insert("date".to_string(), json!(content.create_time.to_rfc3339_opts(SecondsFormat::Secs, true)));
+                if content.target != "POST" {
+                    map.insert("draft".to_string(), json!(true));
+                }
+                if content.kind != "post" {
+                    map.insert("type".to_string(), json!(content.kind));
+                }
+            }
+            _ => {
+                let date = content.create_time.format("%Y-%m-%d %H:%M:%S %z").to_string();
+                map.insert("date".to_string(), json!(date));
+                map.insert("layout".to_string(), json!(content.kind));
+            }
+        }
+        return remove_nulls(Value::Object(map));
+    }
+
+    /// Write a content, get the path of the file.
+    fn export_content(&mut self, format: ExportFormat, content: &Content) -> Result<String> {
+        let front_matter = self.front_matter(format, content);
+        let body = content.content.trim_start_matches(|c| c == '\r' || c == '\n');
+        let path = Path::new(&content.path);
+        let parent = path
+            .parent()
+            .map(|x| x.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default();
+        let name = content
+            .slug()
+            .map(|x| x.to_string())
+            .unwrap_or(path.file_stem().map(|x| x.to_string_lossy().to_string()).unwrap_or_default());
+        let join = |parts: &[&str]| {
+            parts
+                .iter()
+                .filter(|x| !x.is_empty())
+                .map(|x| *x)
+                .collect::<Vec<&str>>()
+                .join("/")
+        };
+        let (target, text) = match format {
+            ExportFormat::Hugo => {
+                // Converted to a TOML value first, so that the tables are written last.
+                let text = toml::Value::try_from(&front_matter)
+                    .and_then(|x| toml::to_string(&x))
+                    .map_err(|err| Error::new("Failed to convert the mark info to TOML.").with_source(err))?;
+                let target = join(&["content", &parent, &format!("{}.md", name)]);
+                (target, format!("+++\n{}+++\n\n{}", text, body))
+            }
+            _ => {
+                let text = serde_yaml::to_string(&front_matter).map_err(|err| {
+                    Error::new("Failed to convert the mark info to YAML.").with_source(err)
+                })?;
+                let text = text.trim_start_matches("---\n").trim_end();
+                let target = if content.kind == "page" {
+                    join(&[&parent, &format!("{}.md", name)])
+                } else if content.target != "POST" {
+                    join(&["_drafts", &parent, &format!("{}.md", name)])
+                } else {
+                    let date = content.create_time.format("%Y-%m-%d");
+                    join(&["_posts", &parent, &format!("{}-{}.md", date, name)])
+                };
+                (target, format!("---\n{}\n---\n\n{}", text, body))
+            }
+        };
+        let target = normalize_output_path(&target).ok_or(Error::new(&format!(
+            "The path \"{}\" is outside the directory.",
+            target
+        )))?;
+        if !self.written.insert(target.clone()) {
+            return Err(Error::new(&format!("Another content is exported as {}.", target)));
+        }
+        self.write(&target, &text)?;
+        return Ok(target);
+    }
+
+    /// Print the report, fail if a content failed to be exported.
+    pub fn finish(&self) -> Result<()> {
+        for diagnostic in &self.diagnostics {
+            diagnostic.print();
+        }
+        println!("{0:>12} {1} content(s)", "Exported".green().bold(), self.exported);
+        let errors = self.diagnostics.iter().filter(|x| x.is_error()).count();
+        if errors > 0 {
+            return Err(Error::new(&format!("Failed to export {} content(s).", errors)).with_kind(ErrorKind::FrontMatter));
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    const TITLE: &str = r#"He said "yes": a story"#;
+
+    /// Export a post, a draft and a page of a new temporary site, get the directory of the export.
+    fn export(name: &str, format: ExportFormat) -> PathBuf {
+        let root = env::temp_dir().join(format!("ims-test-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("content")).unwrap();
+        let contents = [
+            ("post.md", json!({"title": TITLE, "target": "POST", "tags": ["a", "b"], "meta": {"cover": "a.png"}})),
+            ("draft.md", json!({"title": "Draft: one", "target": "DRAFT"})),
+            ("about.md", json!({"title": "About", "target": "POST", "kind": "page"})),
+        ];
+        for &(path, ref mark) in &contents {
+            let mut mark = mark.clone();
+            mark["create_time"] = json!("2018-01-02T03:04:05Z");
+            let text = format!("``````` json\n{}\n```````\n# {}\n", mark, path);
+            fs::write(root.join("content").join(path), text).unwrap();
+        }
+        let mut site = serde_json::from_str::<Site>("{}").unwrap();
+        site.root = root.to_string_lossy().to_string();
+        let directory = root.join("export");
+        let mut exporter = Exporter::new(&site, &directory, false).unwrap();
+        exporter.export(format).unwrap();
+        exporter.finish().unwrap();
+        return root;
+    }
+
+    /// Split the front matter between the delimiters from the body.
+    fn split<'a>(text: &'a str, delimiter: &str) -> (&'a str, &'a str) {
+        let separator = format!("{}\n", delimiter);
+        let mut parts = text.splitn(3, separator.as_str());
+        assert_eq!(parts.next(), Some(""));
+        return (parts.next().unwrap(), parts.next().unwrap());
+    }
+
+    #[test]
+    fn hugo_front_matter_parses_back() {
+        let root = export("export-hugo", ExportFormat::Hugo);
+        let read = |path: &str| fs::read_to_string(root.join("export/content").join(path)).unwrap();
+        let (post, draft, page) = (read("post.md"), read("draft.md"), read("about.md"));
+        fs::remove_dir_all(&root).unwrap();
+
+        let (mark, body) = split(&post, "+++");
+        assert_eq!(body, "\n# post.md\n");
+        let mark = toml::from_str::<toml::Value>(mark).unwrap();
+        assert_eq!(mark["title"].as_str(), Some(TITLE));
+        assert_eq!(mark["tags"], toml::Value::try_from(vec!["a", "b"]).unwrap());
+        assert_eq!(mark["cover"].as_str(), Some("a.png"));
+        assert_eq!(mark["date"].as_str(), Some("2018-01-02T03:04:05Z"));
+        assert!(mark.get("draft").is_none());
+        assert!(mark.get("type").is_none());
+
+        let mark = toml::from_str::<toml::Value>(split(&draft, "+++").0).unwrap();
+        assert_eq!(mark["title"].as_str(), Some("Draft: one"));
+        assert_eq!(mark["draft"].as_bool(), Some(true));
+
+        let mark = toml::from_str::<toml::Value>(split(&page, "+++").0).unwrap();
+        assert_eq!(mark["type"].as_str(), Some("page"));
+    }
+
+    #[test]
+    fn jekyll_front_matter_parses_back() {
+        let root = export("export-jekyll", ExportFormat::Jekyll);
+        let read = |path: &str| fs::read_to_string(root.join("export").join(path)).unwrap();
+        let (post, draft, page) = (read("_posts/2018-01-02-post.md"), read("_drafts/draft.md"), read("about.md"));
+        fs::remove_dir_all(&root).unwrap();
+
+        let (mark, body) = split(&post, "---");
+        assert_eq!(body, "\n# post.md\n");
+        let mark = serde_yaml::from_str::<Value>(mark).unwrap();
+        assert_eq!(mark["title"], json!(TITLE));
+        assert_eq!(mark["tags"], json!(["a", "b"]));
+        assert_eq!(mark["cover"], json!("a.png"));
+        assert_eq!(mark["date"], json!("2018-01-02 03:04:05 +0000"));
+        assert_eq!(mark["layout"], json!("post"));
+
+        let mark = serde_yaml::from_str::<Value>(split(&draft, "---").0).unwrap();
+        assert_eq!(mark["title"], json!("Draft: one"));
+
+        let mark = serde_yaml::from_str::<Value>(split(&page, "---").0).unwrap();
+        assert_eq!(mark["layout"], json!("page"));
+    }
+
+    #[test]
+    fn json_model_parses_back() {
+        let root = export("export-json", ExportFormat::Json);
+        let text = fs::read_to_string(root.join("export/model.json")).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        let model = serde_json::from_str::<Value>(&text).unwrap();
+        let contents: Vec<Content> = serde_json::from_value(model["contents"].clone()).unwrap();
+        let titles: Vec<&str> = contents.iter().map(|x| x.title.as_str()).collect();
+        assert!(titles.contains(&TITLE));
+        assert!(!titles.contains(&"Draft: one"));
+        let post = contents.iter().find(|x| x.title == TITLE).unwrap();
+        assert_eq!(post.tags, vec!["a", "b"]);
+        assert_eq!(post.create_time, Utc.ymd(2018, 1, 2).and_hms(3, 4, 5));
+        assert_eq!(model["tags"][0]["name"], json!("a"));
+    }
+}
@@ -0,0 +1,674 @@
+use std;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+use colored::*;
+use regex::{Captures, Regex};
+use serde_json::{self, Map, Value};
+use serde_yaml;
+use toml;
+use uuid::Uuid;
+
+use super::super::infrastructure::*;
+use super::content::title_from_path;
+use super::{Content, Diagnostic, Site};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// The static site generators whose posts can be imported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Generator {
+    Hexo,
+    Jekyll,
+    Hugo,
+}
+
+impl Generator {
+    pub fn from_name(name: &str) -> Option<Generator> {
+        return match name {
+            "hexo" => Some(Generator::Hexo),
+            "jekyll" => Some(Generator::Jekyll),
+            "hugo" => Some(Generator::Hugo),
+            _ => None,
+        };
+    }
+
+    /// Directories of the posts in the site, and whether the posts in it are drafts.
+    fn post_directories(&self) -> &'static [(&'static str, bool)] {
+        return match *self {
+            Generator::Hexo => &[("source/_posts", false), ("source/_drafts", true)],
+            Generator::Jekyll => &[("_posts", false), ("_drafts", true)],
+            Generator::Hugo => &[("content", false)],
+        };
+    }
+
+    /// Directory of the files served from the root of the site.
+    fn static_directory(&self) -> &'static str {
+        return match *self {
+            Generator::Hexo => "source",
+            Generator::Jekyll => "",
+            Generator::Hugo => "static",
+        };
+    }
+}
+
+lazy_static! {
+    static ref TEMPLATE_TAG: Regex = Regex::new(r"\{%.*?%\}|\{\{.*?\}\}").unwrap();
+    static ref LINK: Regex = Regex::new(
+        r#"(?P<head>!?\[[^\]\n]*\]\(\s*<?)(?P<url>[^)\s>]+)|(?P<attribute>(?:src|href)\s*=\s*["'])(?P<value>[^"']+)"#,
+    ).unwrap();
+    static ref DATED_NAME: Regex = Regex::new(r"^(\d{4}-\d{2}-\d{2})-(.+)$").unwrap();
+    static ref ASSET_IMG: Regex = Regex::new(r"\{%\s*asset_img[ \t]+([^\s%]+)[ \t]*(.*?)[ \t]*%\}").unwrap();
+    static ref ASSET_PATH: Regex = Regex::new(r"\{%\s*asset_path[ \t]+([^\s%]+)[ \t]*%\}").unwrap();
+}
+
+/// Extensions of the posts which are not markdown, they are reported instead of imported.
+const UNSUPPORTED_POSTS: &[&str] = &["html", "htm", "textile", "org", "adoc", "asciidoc", "rst"];
+
+fn is_markdown(path: &Path) -> bool {
+    return match path.extension().and_then(|x| x.to_str()) {
+        Some(extension) => ["md", "markdown", "mdown", "mkd"].contains(&extension.to_lowercase().as_str()),
+        None => false,
+    };
+}
+
+/// Get the path relative to the parent, separated by `/`.
+fn relative_path(parent: &Path, path: &Path) -> String {
+    let path = path.strip_prefix(parent).unwrap_or(path);
+    return path
+        .components()
+        .map(|x| x.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<String>>()
+        .join("/");
+}
+
+/// Get the value and the markdown of a file with YAML, TOML or JSON front matter.
+fn parse_front_matter(text: &str) -> Result<(Map<String, Value>, &str)> {
+    let text = text.trim_start_matches('\u{feff}');
+    let to_map = |value: Value| match value {
+        Value::Object(map) => Ok(map),
+        Value::Null => Ok(Map::new()),
+        _ => Err(Error::new("The front matter is not a map.").with_kind(ErrorKind::FrontMatter)),
+    };
+    if text.starts_with('{') {
+        let mut stream = serde_json::Deserializer::from_str(text).into_iter::<Value>();
+        let value = match stream.next() {
+            Some(value) => value.map_err(|err| {
+                Error::new("Failed to parse the JSON front matter.")
+                    .with_kind(ErrorKind::FrontMatter)
+                    .with_source(err)
+            })?,
+            None => Value::Null,
+        };
+        let offset = stream.byte_offset();
+        return Ok((to_map(value)?, &text[offset..]));
+    }
+    let first_end = text.find('\n').map(|x| x + 1).unwrap_or(text.len());
+    let delimiter = text[..first_end].trim();
+    if delimiter != "---" && delimiter != "+++" {
+        return Ok((Map::new(), text));
+    }
+    let mut index = first_end;
+    while index < text.len() {
+        let end = text[index..].find('\n').map(|x| index + x + 1).unwrap_or(text.len());
+        let line = text[index..end].trim();
+        if line == delimiter || (delimiter == "---" && line == "...") {
+            let mark = &text[first_end..index];
+            if mark.trim().is_empty() {
+                return Ok((Map::new(), &text[end..]));
+            }
+            let value = if delimiter == "---" {
+                serde_yaml::from_str::<Value>(mark).map_err(|err| {
+                    Error::new("Failed to parse the YAML front matter.")
+                        .with_kind(ErrorKind::FrontMatter)
+                        .with_source(err)
+                })?
+            } else {
+                let value = toml::from_str::<toml::Value>(mark).map_err(|err| {
+                    Error::new("Failed to parse the TOML front matter.")
+                        .with_kind(ErrorKind::FrontMatter)
+                        .with_source(err)
+                })?;
+                toml_to_json(value)
+            };
+            return Ok((to_map(value)?, &text[end..]));
+        }
+        index = end;
+    }
+    return Err(Error::new("The front matter is not closed.").with_kind(ErrorKind::FrontMatter));
+}
+
+fn toml_to_json(value: toml::Value) -> Value {
+    return match value {
+        toml::Value::String(value) => Value::String(value),
+        toml::Value::Integer(value) => json!(value),
+        toml::Value::Float(value) => json!(value),
+        toml::Value::Boolean(value) => Value::Bool(value),
+        toml::Value::Datetime(value) => Value::String(value.to_string()),
+        toml::Value::Array(list) => Value::Array(list.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_json(value)))
+                .collect(),
+        ),
+    };
+}
+
+/// Parse the dates written by the other generators, a date without the time zone is UTC.
+pub fn parse_any_date(text: &str) -> Option<DateTime<Utc>> {
+    let text = text.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(text) {
+        return Some(date.with_timezone(&Utc));
+    }
+    for format in &["%Y-%m-%d %H:%M:%S %z", "%Y-%m-%d %H:%M:%S%z", "%Y-%m-%dT%H:%M:%S%z", "%a, %d %b %Y %H:%M:%S %z"] {
+        if let Ok(date) = DateTime::parse_from_str(text, format) {
+            return Some(date.with_timezone(&Utc));
+        }
+    }
+    for format in &["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M", "%Y/%m/%d %H:%M:%S", "%Y/%m/%d %H:%M"] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(text, format) {
+            return Some(Utc.from_utc_datetime(&date));
+        }
+    }
+    for format in &["%Y-%m-%d", "%Y/%m/%d"] {
+        if let Ok(date) = NaiveDate::parse_from_str(text, format) {
+            return Some(Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap()));
+        }
+    }
+    return None;
+}
+
+/// Get the list of strings from a string or a (nested) list, e.g. the tags.
+fn to_list(value: &Value, split_whitespace: bool) -> Vec<String> {
+    return match *value {
+        Value::String(ref text) if split_whitespace => text.split_whitespace().map(|x| x.to_string()).collect(),
+        Value::String(ref text) => text
+            .split(',')
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())
+            .collect(),
+        Value::Array(ref list) => list.iter().flat_map(|x| to_list(x, false)).collect(),
+        Value::Null => vec![],
+        ref value => vec![value.to_string()],
+    };
+}
+
+/// Convert the Hexo tags of the post assets to markdown, e.g. `{% asset_img a.png A %}`.
+fn convert_asset_tags(body: &str) -> String {
+    let body = ASSET_IMG.replace_all(body, "![$2]($1)");
+    return ASSET_PATH.replace_all(&body, "$1").to_string();
+}
+
+fn is_external(url: &str) -> bool {
+    return url.starts_with("//")
+        || url.starts_with('#')
+        || url.starts_with("mailto:")
+        || url.starts_with("data:")
+        || url.contains("://");
+}
+
+/// Writes the imported contents and assets into a site, and collects what could not be mapped.
+pub struct Importer<'a> {
+    site: &'a Site,
+    /// Overwrite the existing contents.
+    force: bool,
+    pub imported: usize,
+    pub assets: usize,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Contents and assets written, relative to the content and static directory.
+    written: BTreeSet<String>,
+    copied: BTreeSet<String>,
+}
+
+impl<'a> Importer<'a> {
+    pub fn new(site: &'a Site, force: bool) -> Importer<'a> {
+        return Importer {
+            site: site,
+            force: force,
+            imported: 0,
+            assets: 0,
+            diagnostics: vec![],
+            written: BTreeSet::new(),
+            copied: BTreeSet::new(),
+        };
+    }
+
+    /// Get the normalized path of a content to import, the path is relative to the content
+    /// directory.
+    ///
+    /// `None` if the content is skipped: an existing content is kept unless `force`, and
+    /// only the first imported file of a path is written.
+    pub fn reserve(&mut self, source: &str, path: &str) -> Result<Option<String>> {
+        let path = normalize_output_path(path).ok_or(Error::new(&format!(
+            "The path \"{}\" is outside the content directory.",
+            path
+        )))?;
+        if self.written.contains(&path) {
+            self.diagnostics.push(Diagnostic::error(
+                source,
+                &format!("Another file is imported as {}.", path),
+            ));
+            return Ok(None);
+        }
+        let file_path = Path::new(&self.site.get_content_path()?).join(&path);
+        if file_path.exists() && !self.force {
+            self.diagnostics.push(Diagnostic::warning(
+                source,
+                &format!("The content {} exists, use --force to overwrite it.", path),
+            ));
+            return Ok(None);
+        }
+        self.written.insert(path.clone());
+        return Ok(Some(path));
+    }
+
+    /// Write a content reserved by `reserve`.
+    pub fn write_content(&mut self, source: &str, path: &str, mark: &Value, body: &str) -> Result<()> {
+        let file_path = Path::new(&self.site.get_content_path()?).join(path);
+        if let Some(parent_path) = file_path.parent() {
+            fs::DirBuilder::new()
+                .recursive(true)
+                .create(parent_path)
+                .map_err(|err| {
+                    Error::new("An error occurred while creating parent directory.").with_source(err)
+                })?;
+        }
+        fs::write(&file_path, Content::format_text(mark, body)?).map_err(|err| {
+            Error::new("An error occurred while save file.")
+                .with_path(&file_path.to_string_lossy())
+                .with_source(err)
+        })?;
+        println!("{0:>12} {1} -> {2}", "Converted".green().bold(), source, path);
+        self.imported += 1;
+        return Ok(());
+    }
+
+    /// Copy a file into the static directory, the path is relative to the static directory.
+    pub fn copy_asset(&mut self, source: &Path, path: &str) -> Result<()> {
+        if self.copied.contains(path) {
+            return Ok(());
+        }
+        let file_path = Path::new(&self.site.get_static_path()?).join(path);
+        if let Some(parent_path) = file_path.parent() {
+            fs::DirBuilder::new()
+                .recursive(true)
+                .create(parent_path)
+                .map_err(|err| {
+                    Error::new("An error occurred while creating parent directory.").with_source(err)
+                })?;
+        }
+        fs::copy(source, &file_path).map_err(|err| {
+            Error::new("Failed to copy the asset.")
+                .with_path(&source.to_string_lossy())
+                .with_source(err)
+        })?;
+        self.copied.insert(path.to_string());
+        self.assets += 1;
+        return Ok(());
+    }
+
+    /// Get the url of a file in the static directory.
+    pub fn asset_url(&self, path: &str) -> String {
+        return format!("{}/{}", self.site.address.trim_end_matches('/'), path);
+    }
+
+    /// Report the template tags of the other generators, they are kept as text.
+    fn check_template_tags(&mut self, source: &str, body: &str, first_line: usize) {
+        for found in TEMPLATE_TAG.find_iter(body) {
+            let line = first_line + body[..found.start()].matches('\n').count();
+            self.diagnostics.push(
+                Diagnostic::warning(source, &format!("The template tag {} is kept as text.", found.as_str()))
+                    .with_line(Some(line)),
+            );
+        }
+    }
+
+    /// Copy the local files linked by the markdown, and point the links to the copies.
+    ///
+    /// The absolute links are looked up in `static_root` and kept as they are, the relative
+    /// links are looked up in `directories` and copied under `prefix`.
+    fn copy_linked_assets(
+        &mut self,
+        source: &str,
+        body: &str,
+        static_root: &Path,
+        directories: &[PathBuf],
+        prefix: &str,
+    ) -> String {
+        return LINK
+            .replace_all(body, |caps: &Captures| {
+                let (head, url) = match caps.name("head") {
+                    Some(head) => (head.as_str(), caps.name("url").unwrap().as_str()),
+                    None => (caps.name("attribute").unwrap().as_str(), caps.name("value").unwrap().as_str()),
+                };
+                let is_image = head.starts_with('!') || head.starts_with("src");
+                match self.copy_linked_asset(url, static_root, directories, prefix) {
+                    Ok(Some(url)) => return format!("{}{}", head, url),
+                    Ok(None) if is_image => self
+                        .diagnostics
+                        .push(Diagnostic::warning(source, &format!("The asset {} is not found.", url))),
+                    Ok(None) => {}
+                    Err(err) => self.diagnostics.push(Diagnostic::error(source, &err.reason())),
+                }
+                return caps[0].to_string();
+            })
+            .to_string();
+    }
+
+    /// Copy the file of the url if it is a local asset, get the new url.
+    fn copy_linked_asset(
+        &mut self,
+        url: &str,
+        static_root: &Path,
+        directories: &[PathBuf],
+        prefix: &str,
+    ) -> Result<Option<String>> {
+        if is_external(url) {
+            return Ok(Some(url.to_string()));
+        }
+        let end = url.find(|c| c == '?' || c == '#').unwrap_or(url.len());
+        let (path, suffix) = (url[..end].replace("%20", " "), &url[end..]);
+        let is_asset = |file: &Path| file.is_file() && !is_markdown(file);
+        if path.starts_with('/') {
+            let relative = match normalize_output_path(&path) {
+                Some(relative) => relative,
+                None => return Ok(None),
+            };
+            let file = static_root.join(&relative);
+            if !is_asset(&file) {
+                // Maybe a page of the site.
+                return Ok(Some(url.to_string()));
+            }
+            self.copy_asset(&file, &relative)?;
+            return Ok(Some(url.to_string()));
+        }
+        for directory in directories {
+            let file = directory.join(&path);
+            if !is_asset(&file) {
+                continue;
+            }
+            let target = match normalize_output_path(&format!("{}/{}", prefix, path)) {
+                Some(target) => target,
+                None => return Ok(None),
+            };
+            self.copy_asset(&file, &target)?;
+            return Ok(Some(format!("{}{}", self.asset_url(&target), suffix)));
+        }
+        return Ok(None);
+    }
+
+    /// Import the posts of a Hexo, Jekyll or Hugo site.
+    pub fn import_site(&mut self, generator: Generator, root: &Path) -> Result<()> {
+        let mut found = false;
+        for &(directory, is_draft) in generator.post_directories() {
+            let directory_path = root.join(directory);
+            if !directory_path.is_dir() {
+                continue;
+            }
+            found = true;
+            let mut files = get_all_file(&directory_path)?;
+            files.sort();
+            for file in files {
+                let file_path = Path::new(&file);
+                let extension = file_path
+                    .extension()
+                    .map(|x| x.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                if UNSUPPORTED_POSTS.contains(&extension.as_str()) {
+                    self.diagnostics.push(Diagnostic::warning(
+                        &file,
+                        "Only the markdown posts are imported.",
+                    ));
+                    continue;
+                }
+                if !is_markdown(file_path) {
+                    // The assets are copied when they are linked by the posts.
+                    continue;
+                }
+                let relative = relative_path(&directory_path, file_path);
+                if let Err(err) = self.import_post(generator, root, file_path, &relative, is_draft) {
+                    self.diagnostics.push(Diagnostic::error(&file, &err.reason()));
+                }
+            }
+        }
+        if !found {
+            let directories: Vec<&str> = generator.post_directories().iter().map(|x| x.0).collect();
+            return Err(Error::new(&format!(
+                "Failed to find the posts, expected the directory {}.",
+                directories.join(" or ")
+            )).with_kind(ErrorKind::Config)
+                .with_path(&root.to_string_lossy()));
+        }
+        return Ok(());
+    }
+
+    /// Import a post, `relative` is the path in the directory of the posts.
+    fn import_post(
+        &mut self,
+        generator: Generator,
+        root: &Path,
+        file_path: &Path,
+        relative: &str,
+        is_draft: bool,
+    ) -> Result<()> {
+        let source = file_path.to_string_lossy().to_string();
+        let text = fs::read_to_string(file_path)
+            .map_err(|err| Error::new("Failed to read file.").with_source(err))?;
+        let (front, body) = parse_front_matter(&text)?;
+        let first_line = text[..text.len() - body.len()].matches('\n').count() + 1;
+
+        // The path of the content, e.g. `foo.md` from Jekyll `2018-01-01-foo.md` and
+        // `posts/foo.md` from Hugo `posts/foo/index.md`.
+        let mut path = Path::new(relative).with_extension("md");
+        let mut date_from_name = None;
+        let name = path.file_stem().map(|x| x.to_string_lossy().to_string()).unwrap_or_default();
+        if generator == Generator::Hugo && name == "_index" {
+            self.diagnostics.push(Diagnostic::warning(&source, "The list pages are not imported."));
+            return Ok(());
+        }
+        if generator == Generator::Hugo && name == "index" && path.parent().map_or(false, |x| x != Path::new("")) {
+            path = path.parent().unwrap().with_extension("md");
+        }
+        if let Some(caps) = DATED_NAME.captures(&name) {
+            if generator == Generator::Jekyll {
+                date_from_name = parse_any_date(&caps[1]);
+                path = path.with_file_name(format!("{}.md", &caps[2]));
+            }
+        }
+        let path = match self.reserve(&source, &relative_path(Path::new(""), &path))? {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let mut title = title_from_path(&path);
+        let mut description = String::new();
+        let mut tags: Vec<String> = vec![];
+        let mut categories: Vec<String> = vec![];
+        let mut date = None;
+        let mut draft = is_draft;
+        let mut kind = None;
+        let mut id = None;
+        let mut meta = Map::new();
+        for (key, value) in front {
+            match key.as_str() {
+                "title" => title = value.as_str().map(|x| x.to_string()).unwrap_or(value.to_string()),
+                "description" | "summary" | "excerpt" => {
+                    description = value.as_str().map(|x| x.to_string()).unwrap_or(value.to_string())
+                }
+                "tags" => tags.extend(to_list(&value, generator == Generator::Jekyll)),
+                "categories" | "category" => categories.extend(to_list(&value, generator == Generator::Jekyll)),
+                "date" => {
+                    date = value.as_str().and_then(parse_any_date);
+                    if date.is_none() {
+                        self.diagnostics.push(
+                            Diagnostic::warning(&source, "Failed to parse the date, kept in meta.").with_key(&key),
+                        );
+                        meta.insert(key, value);
+                    }
+                }
+                "draft" => draft = draft || value.as_bool() == Some(true),
+                "published" => draft = draft || value.as_bool() == Some(false),
+                "layout" | "type" => kind = value.as_str().map(|x| x.to_string()),
+                "slug" => {
+                    meta.insert(key, value);
+                }
+                // Written by `ims export`.
+                "id" if value.as_str().and_then(|x| Uuid::parse_str(x).ok()).is_some() => {
+                    id = value.as_str().and_then(|x| Uuid::parse_str(x).ok())
+                }
+                _ => {
+                    self.diagnostics.push(
+                        Diagnostic::warning(&source, "Not mapped, kept in meta.").with_key(&key),
+                    );
+                    meta.insert(key, value);
+                }
+            }
+        }
+        if !categories.is_empty() {
+            meta.insert("categories".to_string(), json!(categories));
+        }
+        let date = match date.or(date_from_name) {
+            Some(date) => date,
+            None => {
+                self.diagnostics.push(Diagnostic::warning(
+                    &source,
+                    "No date, the modified time of the file is used.",
+                ));
+                fs::metadata(file_path)
+                    .and_then(|x| x.modified())
+                    .map(DateTime::<Utc>::from)
+                    .unwrap_or(Utc::now())
+            }
+        };
+        let kind = kind.unwrap_or(if generator == Generator::Hugo && !path.contains('/') {
+            "page".to_string()
+        } else {
+            "post".to_string()
+        });
+
+        let body = if generator == Generator::Hexo {
+            convert_asset_tags(body)
+        } else {
+            body.to_string()
+        };
+        self.check_template_tags(&source, &body, first_line);
+        let static_root = root.join(generator.static_directory());
+        // The assets of a Hexo post are in the folder named by the post, the assets of
+        // a Hugo page bundle are beside the `index.md`.
+        let directories = vec![
+            file_path.parent().map(|x| x.to_path_buf()).unwrap_or_default(),
+            file_path.with_extension(""),
+        ];
+        let prefix = path.trim_end_matches(".md").to_string();
+        let body = self.copy_linked_assets(&source, &body, &static_root, &directories, &prefix);
+
+        let mark = json!({
+            "id": id.unwrap_or(Uuid::new_v4()).to_string(),
+            "title": title,
+            "description": description,
+            "target": if draft { "DRAFT" } else { "POST" },
+            "kind": kind,
+            "tags": tags,
+            "create_time": date.to_rfc3339_opts(SecondsFormat::Secs, true),
+            "meta": if meta.is_empty() { Value::Null } else { Value::Object(meta) }
+        });
+        return self.write_content(&source, &path, &mark, body.trim_start_matches(|c| c == '\r' || c == '\n'));
+    }
+
+    /// Print the report, fail if a file failed to be imported.
+    pub fn finish(&self) -> Result<()> {
+        for diagnostic in &self.diagnostics {
+            diagnostic.print();
+        }
+        println!(
+            "{0:>12} {1} content(s), {2} asset(s)",
+            "Imported".green().bold(),
+            self.imported,
+            self.assets
+        );
+        let errors = self.diagnostics.iter().filter(|x| x.is_error()).count();
+        if errors > 0 {
+            return Err(Error::new(&format!("Failed to import {} file(s).", errors)).with_kind(ErrorKind::FrontMatter));
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_front_matter_reads_yaml_toml_and_json() {
+        let (map, body) = parse_front_matter("---\ntitle: \"A: b\"\ntags: [a, b]\n---\n# Body\n").unwrap();
+        assert_eq!(map["title"], json!("A: b"));
+        assert_eq!(map["tags"], json!(["a", "b"]));
+        assert_eq!(body, "# Body\n");
+
+        let (map, body) = parse_front_matter("\u{feff}---\ntitle: A\n...\nBody").unwrap();
+        assert_eq!(map["title"], json!("A"));
+        assert_eq!(body, "Body");
+
+        let text = "+++\ntitle = \"A\"\ndraft = true\ndate = 2018-01-02T03:04:05Z\n+++\nBody";
+        let (map, body) = parse_front_matter(text).unwrap();
+        assert_eq!(map["title"], json!("A"));
+        assert_eq!(map["draft"], json!(true));
+        assert_eq!(map["date"], json!("2018-01-02T03:04:05Z"));
+        assert_eq!(body, "Body");
+
+        let (map, body) = parse_front_matter("{\"title\": \"A\", \"tags\": [\"a\"]}\nBody").unwrap();
+        assert_eq!(map["title"], json!("A"));
+        assert_eq!(map["tags"], json!(["a"]));
+        assert_eq!(body, "\nBody");
+    }
+
+    #[test]
+    fn parse_front_matter_handles_missing_empty_and_broken_front_matters() {
+        let (map, body) = parse_front_matter("# Title\n---\n").unwrap();
+        assert!(map.is_empty());
+        assert_eq!(body, "# Title\n---\n");
+
+        let (map, body) = parse_front_matter("---\n---\nBody").unwrap();
+        assert!(map.is_empty());
+        assert_eq!(body, "Body");
+
+        let error = parse_front_matter("---\ntitle: A\nBody").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::FrontMatter);
+        assert!(parse_front_matter("---\n- a\n---\n").is_err());
+        assert!(parse_front_matter("+++\ntitle = \n+++\n").is_err());
+        assert!(parse_front_matter("{\"title\": }").is_err());
+    }
+
+    #[test]
+    fn parse_any_date_reads_the_formats_of_the_generators() {
+        let date = Utc.ymd(2018, 1, 2).and_hms(3, 4, 5);
+        assert_eq!(parse_any_date("2018-01-02T03:04:05Z"), Some(date));
+        assert_eq!(parse_any_date("2018-01-02T11:04:05+08:00"), Some(date));
+        assert_eq!(parse_any_date("2018-01-02 11:04:05 +0800"), Some(date));
+        assert_eq!(parse_any_date("Tue, 02 Jan 2018 03:04:05 +0000"), Some(date));
+        assert_eq!(parse_any_date(" 2018-01-02 03:04:05 "), Some(date));
+        assert_eq!(parse_any_date("2018/01/02 03:04"), Some(Utc.ymd(2018, 1, 2).and_hms(3, 4, 0)));
+        assert_eq!(parse_any_date("2018-01-02"), Some(Utc.ymd(2018, 1, 2).and_hms(0, 0, 0)));
+        assert_eq!(parse_any_date("yesterday"), None);
+        assert_eq!(parse_any_date(""), None);
+    }
+
+    #[test]
+    fn to_list_flattens_strings_and_lists() {
+        assert_eq!(to_list(&json!("a, b,,c "), false), vec!["a", "b", "c"]);
+        assert_eq!(to_list(&json!("a  b"), true), vec!["a", "b"]);
+        assert_eq!(to_list(&json!(["a, b", ["c"]]), true), vec!["a", "b", "c"]);
+        assert_eq!(to_list(&json!(2018), false), vec!["2018"]);
+        assert!(to_list(&Value::Null, false).is_empty());
+    }
+
+    #[test]
+    fn convert_asset_tags_rewrites_the_hexo_tags() {
+        let body = "{% asset_img cat.png A black cat %}\n{%asset_img dog.jpg%}\n[pdf]({% asset_path a.pdf %})";
+        assert_eq!(convert_asset_tags(body), "![A black cat](cat.png)\n![](dog.jpg)\n[pdf](a.pdf)");
+        assert_eq!(convert_asset_tags("{% raw %}"), "{% raw %}");
+    }
+}
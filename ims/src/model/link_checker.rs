@@ -0,0 +1,313 @@
+use std;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+use regex::Regex;
+
+use super::super::infrastructure::*;
+use super::{Content, Diagnostic, Site};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Check the links in the generated html files.
+pub struct LinkChecker<'a> {
+    site: &'a Site,
+    build_path: PathBuf,
+    /// Prefixes of the external links which are allowed, `None` to skip the external links.
+    allowlist: Option<Vec<String>>,
+    /// Anchors of the html files, loaded on demand.
+    anchors: HashMap<PathBuf, HashSet<String>>,
+    /// Output path to content path, from the build cache.
+    sources: HashMap<String, String>,
+}
+
+fn read_file(path: &Path) -> Result<String> {
+    let mut buffer = String::new();
+    fs::File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut buffer))
+        .map_err(|err| {
+            Error::new("Failed to read file.")
+                .with_path(&path.to_string_lossy())
+                .with_source(err)
+        })?;
+    return Ok(buffer);
+}
+
+/// Decode the `%xx` escapes of an url.
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut result = vec![];
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' && index + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[index + 1..index + 3]).unwrap_or("");
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                result.push(byte);
+                index += 3;
+                continue;
+            }
+        }
+        result.push(bytes[index]);
+        index += 1;
+    }
+    return String::from_utf8_lossy(&result).to_string();
+}
+
+/// Join a relative path without touching the file system, `..` can not leave the root.
+fn normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                result.pop();
+            }
+            Component::Normal(name) => result.push(name),
+            _ => {}
+        }
+    }
+    return result;
+}
+
+impl<'a> LinkChecker<'a> {
+    pub fn new(site: &'a Site, allowlist: Option<Vec<String>>) -> Result<LinkChecker<'a>> {
+        let build_path = PathBuf::from(site.get_build_path()?);
+        if !build_path.exists() {
+            return Err(Error::new("The build directory is not exists, build the site first.")
+                .with_path(&build_path.to_string_lossy()));
+        }
+        // Map the outputs to the content files, so that the broken links can be fixed there.
+        let cache = BuildCache::load(&build_path.join(BUILD_CACHE_FILE));
+        let mut paths = HashMap::new();
+        for content in Content::load_all(site)? {
+            let content_path = Path::new(&site.get_content_path()?).join(&content.path);
+            paths.insert(content.id.to_string(), content_path.to_string_lossy().to_string());
+        }
+        let mut sources = HashMap::new();
+        for (name, record) in cache.outputs.iter() {
+            if let Some(path) = record.content.as_ref().and_then(|id| paths.get(id)) {
+                sources.insert(name.clone(), path.clone());
+            }
+        }
+        return Ok(LinkChecker {
+            site: site,
+            build_path: build_path,
+            allowlist: allowlist,
+            anchors: HashMap::new(),
+            sources: sources,
+        });
+    }
+
+    /// Load the allowlist, one prefix per line, lines starting with `#` are ignored.
+    pub fn load_allowlist(path: &str) -> Result<Vec<String>> {
+        let buffer = read_file(Path::new(path))?;
+        return Ok(buffer
+            .lines()
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty() && !x.starts_with('#'))
+            .map(|x| x.to_string())
+            .collect());
+    }
+
+    pub fn check(&mut self) -> Result<Vec<Diagnostic>> {
+        let re = Regex::new(r#"(?i)\b(?:href|src)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).map_err(|err| {
+            Error::new("An error occurred while resolving the links.").with_source(err)
+        })?;
+        let mut files: Vec<String> = get_all_file(&self.build_path)?
+            .into_iter()
+            .filter(|x| x.ends_with(".html") || x.ends_with(".htm"))
+            .collect();
+        files.sort();
+        let mut diagnostics = vec![];
+        for file in &files {
+            let file_path = Path::new(file);
+            let buffer = read_file(file_path)?;
+            let relative = file_path
+                .strip_prefix(&self.build_path)
+                .map(|x| x.to_path_buf())
+                .unwrap_or(file_path.to_path_buf());
+            for caps in re.captures_iter(&buffer) {
+                let matched = caps.get(1).or(caps.get(2)).unwrap();
+                let link = matched.as_str().trim();
+                let problem = match self.check_link(&relative, link)? {
+                    Some(problem) => problem,
+                    None => continue,
+                };
+                let line = buffer[..matched.start()].matches('\n').count() + 1;
+                let message = format!("{} \"{}\"", problem, link);
+                // Report on the content which the file is generated from.
+                let output = normalize_output_path(&relative.to_string_lossy()).unwrap_or_default();
+                diagnostics.push(match self.sources.get(&output) {
+                    Some(source) => {
+                        Diagnostic::error(source, &format!("{} in {}:{}.", message, file, line))
+                    }
+                    None => Diagnostic::error(file, &format!("{}.", message)).with_line(Some(line)),
+                });
+            }
+        }
+        return Ok(diagnostics);
+    }
+
+    /// Check a link of the file, get the problem if it is broken.
+    fn check_link(&mut self, file: &Path, link: &str) -> Result<Option<String>> {
+        let lower = link.to_lowercase();
+        if link.is_empty()
+            || link == "#"
+            || ["mailto:", "tel:", "javascript:", "data:"]
+                .iter()
+                .any(|x| lower.starts_with(x))
+        {
+            return Ok(None);
+        }
+        let mut link = link.to_string();
+        let address = self.site.address.trim_end_matches('/').to_string();
+        if !address.is_empty() && link.starts_with(&format!("{}/", address)) {
+            link = link[address.len()..].to_string();
+        } else if lower.starts_with("http://") || lower.starts_with("https://") || link.starts_with("//") {
+            return Ok(match self.allowlist {
+                Some(ref allowlist) if !allowlist.iter().any(|x| link.starts_with(x)) => {
+                    Some("External link not in the allowlist".to_string())
+                }
+                _ => None,
+            });
+        } else if lower.contains(':') && !lower.contains('/') {
+            // Other schemes.
+            return Ok(None);
+        }
+
+        let (path, anchor) = match link.find('#') {
+            Some(index) => (link[..index].to_string(), Some(percent_decode(&link[index + 1..]))),
+            None => (link.clone(), None),
+        };
+        let path = match path.find('?') {
+            Some(index) => path[..index].to_string(),
+            None => path,
+        };
+        let path = percent_decode(&path);
+        let target = if path.is_empty() {
+            file.to_path_buf()
+        } else if path.starts_with('/') {
+            normalize(Path::new(&path[1..]))
+        } else {
+            normalize(&file.parent().unwrap_or(Path::new("")).join(&path))
+        };
+        let mut target_path = self.build_path.join(&target);
+        if target_path.is_dir() {
+            target_path = target_path.join("index.html");
+        } else if !target_path.exists() && target_path.extension().is_none() {
+            target_path = target_path.with_extension("html");
+        }
+        if !target_path.is_file() {
+            return Ok(Some("Broken link".to_string()));
+        }
+        if let Some(anchor) = anchor {
+            if anchor.is_empty() {
+                return Ok(None);
+            }
+            let is_html = target_path
+                .extension()
+                .map(|x| x == "html" || x == "htm")
+                .unwrap_or(false);
+            if is_html && !self.get_anchors(&target_path)?.contains(&anchor) {
+                return Ok(Some("Broken anchor".to_string()));
+            }
+        }
+        return Ok(None);
+    }
+
+    fn get_anchors(&mut self, path: &Path) -> Result<&HashSet<String>> {
+        if !self.anchors.contains_key(path) {
+            let re = Regex::new(r#"(?i)\b(?:id|name)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).map_err(|err| {
+                Error::new("An error occurred while resolving the anchors.").with_source(err)
+            })?;
+            let buffer = read_file(path)?;
+            let anchors = re
+                .captures_iter(&buffer)
+                .map(|caps| caps.get(1).or(caps.get(2)).unwrap().as_str().to_string())
+                .collect();
+            self.anchors.insert(path.to_path_buf(), anchors);
+        }
+        return Ok(&self.anchors[path]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+    use std::env;
+    use std::process;
+
+    /// A site with a built tree in a new temporary directory.
+    fn create_site(name: &str) -> Site {
+        let root = env::temp_dir().join(format!("ims-test-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("content")).unwrap();
+        fs::create_dir_all(root.join("build/post")).unwrap();
+        fs::create_dir_all(root.join("build/images")).unwrap();
+        let index = concat!(
+            "<a href=\"post/a.html\">a</a> <a href=\"post/missing.html\">b</a>\n",
+            "<img src=\"/images/a.png\"> <img src='/images/missing.png'>\n",
+            "<a href=\"post/a.html#intro\">c</a> <a href=\"post/a.html#nope\">d</a> <a href=\"#\">e</a>\n",
+            "<a href=\"https://good.example/x\">f</a> <a href=\"https://bad.example/y\">g</a>\n",
+            "<a href=\"mailto:a@example.com\">h</a> <a href=\"post/?page=2\">i</a>\n",
+        );
+        fs::write(root.join("build/index.html"), index).unwrap();
+        fs::write(root.join("build/images/a.png"), "").unwrap();
+        let post = "<h2 id=\"intro\">Intro</h2>\n<a href=\"../index.html\">a</a> <a href=\"../css/a.css\">b</a>\n";
+        fs::write(root.join("build/post/a.html"), post).unwrap();
+        fs::write(root.join("build/post/index.html"), "").unwrap();
+        let mut site = serde_json::from_str::<Site>("{}").unwrap();
+        site.root = root.to_string_lossy().to_string();
+        return site;
+    }
+
+    /// Check the site, get the file names, lines and messages of the problems.
+    fn check(site: &Site, allowlist: Option<Vec<String>>) -> Vec<(String, Option<usize>, String)> {
+        let diagnostics = LinkChecker::new(site, allowlist).unwrap().check().unwrap();
+        fs::remove_dir_all(&site.root).unwrap();
+        return diagnostics
+            .into_iter()
+            .map(|x| {
+                let name = Path::new(&x.path).strip_prefix(&site.root).unwrap().to_string_lossy().replace('\\', "/");
+                (name, x.line, x.message)
+            })
+            .collect();
+    }
+
+    #[test]
+    fn check_reports_the_broken_links_and_anchors() {
+        let site = create_site("links");
+        let problems = check(&site, Some(vec!["https://good.example/".to_string()]));
+        let expected = vec![
+            ("build/index.html", 1, "Broken link \"post/missing.html\"."),
+            ("build/index.html", 2, "Broken link \"/images/missing.png\"."),
+            ("build/index.html", 3, "Broken anchor \"post/a.html#nope\"."),
+            ("build/index.html", 4, "External link not in the allowlist \"https://bad.example/y\"."),
+            ("build/post/a.html", 2, "Broken link \"../css/a.css\"."),
+        ];
+        let expected: Vec<(String, Option<usize>, String)> = expected
+            .into_iter()
+            .map(|(name, line, message)| (name.to_string(), Some(line), message.to_string()))
+            .collect();
+        assert_eq!(problems, expected);
+    }
+
+    #[test]
+    fn check_skips_the_external_links_without_an_allowlist() {
+        let site = create_site("links-external");
+        let problems = check(&site, None);
+        assert_eq!(problems.len(), 4);
+        assert!(problems.iter().all(|x| !x.2.starts_with("External")));
+    }
+
+    #[test]
+    fn load_allowlist_skips_the_comments() {
+        let path = env::temp_dir().join(format!("ims-test-allowlist-{}", process::id()));
+        fs::write(&path, "# Links\nhttps://a.example/\n\n  https://b.example/  \n").unwrap();
+        let allowlist = LinkChecker::load_allowlist(&path.to_string_lossy()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(allowlist, vec!["https://a.example/", "https://b.example/"]);
+    }
+}
@@ -1,8 +1,23 @@
+mod build_report;
 mod content;
+mod diagnostic;
+mod exporter;
+mod importer;
+mod link_checker;
+mod output_format;
+mod search_index;
 mod site;
+mod wordpress;
 
-pub use self::site::Site;
-pub use self::content::Content;
+pub use self::site::{Site, SiteSource};
+pub use self::content::{Content, ListOptions, NewOptions, Overwrite, LIST_COLUMNS};
+pub use self::build_report::{BuildReport, ReportItem};
+pub use self::diagnostic::Diagnostic;
+pub use self::exporter::{ExportFormat, Exporter};
+pub use self::importer::{Generator, Importer};
+pub use self::link_checker::LinkChecker;
+pub use self::output_format::OutputFormat;
+pub use self::search_index::{SearchConfig, SearchIndex};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ItemGroup<T>{
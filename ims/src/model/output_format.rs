@@ -0,0 +1,51 @@
+use super::super::infrastructure::normalize_output_path;
+
+/// An output written for each content of a kind, declared in `outputs` of `site.json`.
+///
+/// ``` json
+/// "outputs": {
+///     "post": [
+///         { "format": "html", "template": "post.hbs", "directory": "post" },
+///         { "format": "json", "directory": "data/post" }
+///     ]
+/// }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OutputFormat {
+    /// Name of the format, `json` is serialized from the content, the others are rendered
+    /// with `template`.
+    pub format: String,
+    /// Template in the theme, rendered with `site` and `content`.
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Extension of the files, default to the name of the format.
+    #[serde(default)]
+    pub extension: Option<String>,
+    /// Directory of the files in the build directory, the files are named by the content ids.
+    #[serde(default)]
+    pub directory: String,
+}
+
+impl OutputFormat {
+    /// Whether the outputs are serialized from the content instead of rendered.
+    pub fn is_json(&self) -> bool {
+        return self.format == "json";
+    }
+
+    /// Name of the format in the reports and the build cache.
+    pub fn key(&self, kind: &str) -> String {
+        return format!("outputs/{}/{}", kind, self.format);
+    }
+
+    pub fn get_extension(&self) -> &str {
+        return match self.extension {
+            Some(ref extension) => extension.trim_start_matches('.'),
+            None => &self.format,
+        };
+    }
+
+    /// Path of the output of a content relative to the build directory.
+    pub fn get_path(&self, id: &str) -> Option<String> {
+        return normalize_output_path(&format!("{}/{}.{}", self.directory, id, self.get_extension()));
+    }
+}
@@ -0,0 +1,321 @@
+use std;
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::prelude::*;
+use serde_json;
+
+use super::super::infrastructure::*;
+use super::Content;
+
+type Result<T> = std::result::Result<T, Error>;
+
+fn default_enabled() -> bool {
+    true
+}
+fn default_directory() -> String {
+    "search".to_string()
+}
+fn default_shard_size() -> usize {
+    5000
+}
+
+/// Config of the search index written by `ims build`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SearchConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Directory of the index in the build directory.
+    #[serde(default = "default_directory")]
+    pub directory: String,
+    /// Average number of tokens in a shard, the tokens are split into `ceil(tokens / shard_size)` shards.
+    #[serde(default = "default_shard_size")]
+    pub shard_size: usize,
+}
+
+impl Default for SearchConfig {
+    fn default() -> SearchConfig {
+        return SearchConfig {
+            enabled: default_enabled(),
+            directory: default_directory(),
+            shard_size: default_shard_size(),
+        };
+    }
+}
+
+/// A content in the search index.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SearchDocument {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub tags: Vec<String>,
+    pub kind: String,
+    pub create_time: DateTime<Utc>,
+    /// The text of the content without the markup.
+    #[serde(skip)]
+    pub text: String,
+}
+
+/// A document matching the query of a search.
+#[derive(Serialize, Debug)]
+pub struct SearchResult<'a> {
+    #[serde(flatten)]
+    pub document: &'a SearchDocument,
+    pub score: u32,
+    /// Text around the first match, html escaped, the matches are wrapped with `<mark>`.
+    pub snippet: String,
+}
+
+fn escape_html(text: &str) -> String {
+    return text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;");
+}
+
+/// Get the text around the first match of the tokens, with the matches highlighted.
+fn create_snippet(text: &str, tokens: &[String], width: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    // One lowercase char for each char, so that the positions are the same.
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    let mut marked = vec![false; chars.len()];
+    for token in tokens {
+        let token: Vec<char> = token.chars().collect();
+        if token.is_empty() || token.len() > lower.len() {
+            continue;
+        }
+        for start in 0..lower.len() - token.len() + 1 {
+            if lower[start..start + token.len()] == token[..] {
+                for flag in marked[start..start + token.len()].iter_mut() {
+                    *flag = true;
+                }
+            }
+        }
+    }
+    let first = marked.iter().position(|x| *x).unwrap_or(0);
+    let start = first.saturating_sub(width / 2);
+    let end = std::cmp::min(start + width, chars.len());
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push_str("...");
+    }
+    let mut index = start;
+    while index < end {
+        let highlighted = marked[index];
+        let mut next = index;
+        while next < end && marked[next] == highlighted {
+            next += 1;
+        }
+        let part: String = chars[index..next].iter().collect();
+        if highlighted {
+            snippet.push_str(&format!("<mark>{}</mark>", escape_html(&part)));
+        } else {
+            snippet.push_str(&escape_html(&part));
+        }
+        index = next;
+    }
+    if end < chars.len() {
+        snippet.push_str("...");
+    }
+    return snippet;
+}
+
+/// Inverted index of the contents, the score of a token in a document is weighted by the field.
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    pub documents: Vec<SearchDocument>,
+    /// Token to the indexes of the documents and the scores, sorted by the score.
+    pub tokens: BTreeMap<String, Vec<(usize, u32)>>,
+}
+
+impl SearchIndex {
+    /// Index the contents, the markdown of them must be rendered.
+    pub fn new(contents: &[Content]) -> SearchIndex {
+        let mut index = SearchIndex::default();
+        for (position, content) in contents.iter().enumerate() {
+            let document = SearchDocument {
+                id: content.id.to_string(),
+                title: content.title.clone(),
+                description: content.description.clone(),
+                tags: content.tags.clone(),
+                kind: content.kind.clone(),
+                create_time: content.create_time,
+                text: strip_html(&content.html),
+            };
+            let mut scores: HashMap<String, u32> = HashMap::new();
+            {
+                let fields = [
+                    (document.title.as_str(), 10),
+                    (&document.tags.join(" "), 5),
+                    (document.description.as_str(), 3),
+                    (document.text.as_str(), 1),
+                ];
+                for &(text, weight) in fields.iter() {
+                    for token in tokenize(text) {
+                        *scores.entry(token).or_insert(0) += weight;
+                    }
+                }
+            }
+            for (token, score) in scores {
+                index.tokens.entry(token).or_insert(vec![]).push((position, score));
+            }
+            index.documents.push(document);
+        }
+        for list in index.tokens.values_mut() {
+            list.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        }
+        return index;
+    }
+
+    /// Find the documents having all tokens of the query, the best matches first.
+    pub fn search<'a>(&'a self, query: &str, limit: usize) -> Vec<SearchResult<'a>> {
+        let mut tokens = tokenize(query);
+        tokens.sort();
+        tokens.dedup();
+        if tokens.is_empty() {
+            return vec![];
+        }
+        let mut scores: HashMap<usize, (usize, u32)> = HashMap::new();
+        for token in &tokens {
+            if let Some(list) = self.tokens.get(token) {
+                for &(document, score) in list {
+                    let entry = scores.entry(document).or_insert((0, 0));
+                    entry.0 += 1;
+                    entry.1 += score;
+                }
+            }
+        }
+        let mut matches: Vec<(usize, u32)> = scores
+            .into_iter()
+            .filter(|&(_, (count, _))| count == tokens.len())
+            .map(|(document, (_, score))| (document, score))
+            .collect();
+        matches.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        return matches
+            .into_iter()
+            .take(limit)
+            .map(|(position, score)| {
+                let document = &self.documents[position];
+                let text = format!("{} {}", document.description, document.text);
+                SearchResult {
+                    document: document,
+                    score: score,
+                    snippet: create_snippet(text.trim(), &tokens, 120),
+                }
+            })
+            .collect();
+    }
+
+    /// Get the files of the index, the paths are relative to the build directory.
+    ///
+    /// `index.json` has the documents and the number of shards, a token is in the shard
+    /// `{hash_token(token) % shards}.json`.
+    pub fn to_files(&self, config: &SearchConfig) -> Result<Vec<(String, String)>> {
+        let size = std::cmp::max(config.shard_size, 1);
+        let count = std::cmp::max((self.tokens.len() + size - 1) / size, 1);
+        let mut shards: Vec<BTreeMap<&str, &Vec<(usize, u32)>>> = vec![BTreeMap::new(); count];
+        for (token, list) in self.tokens.iter() {
+            shards[hash_token(token) as usize % count].insert(token, list);
+        }
+        let path = |name: &str| {
+            normalize_output_path(&format!("{}/{}", config.directory, name)).ok_or(
+                Error::new("The directory of the search index is outside the build directory.")
+                    .with_kind(ErrorKind::Config),
+            )
+        };
+        let to_json = |value: &serde_json::Value| {
+            serde_json::to_string(value)
+                .map_err(|err| Error::new("Failed to serialize the search index.").with_source(err))
+        };
+        let mut files = vec![];
+        let index = json!({
+            "version": 1,
+            "shards": count,
+            "documents": self.documents
+        });
+        files.push((path("index.json")?, to_json(&index)?));
+        for (number, shard) in shards.iter().enumerate() {
+            files.push((path(&format!("{}.json", number))?, to_json(&json!(shard))?));
+        }
+        return Ok(files);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A rendered content.
+    fn content(title: &str, tags: &[&str], html: &str) -> Content {
+        let mut content: Content = serde_json::from_value(json!({"title": title, "description": "", "tags": tags})).unwrap();
+        content.html = html.to_string();
+        return content;
+    }
+
+    fn titles(results: &[SearchResult]) -> Vec<String> {
+        return results.iter().map(|x| x.document.title.clone()).collect();
+    }
+
+    #[test]
+    fn search_finds_the_documents_having_every_token() {
+        let index = SearchIndex::new(&[
+            content("Rust", &[], "<p>A static blog generator.</p>"),
+            content("Web server", &["rust"], "<p>Serve the blog.</p>"),
+            content("Notes", &[], "<p>Rust and Go.</p>"),
+        ]);
+        assert_eq!(titles(&index.search("rust blog", 10)), vec!["Rust", "Web server"]);
+        assert_eq!(titles(&index.search("BLOG static", 10)), vec!["Rust"]);
+        assert!(index.search("rust python", 10).is_empty());
+        assert!(index.search(" ,. ", 10).is_empty());
+    }
+
+    #[test]
+    fn search_ranks_by_the_weighted_score() {
+        let index = SearchIndex::new(&[
+            content("Notes", &[], "<p>Rust</p>"),
+            content("Web server", &["rust"], ""),
+            content("Rust", &[], ""),
+        ]);
+        let results = index.search("rust", 10);
+        assert_eq!(titles(&results), vec!["Rust", "Web server", "Notes"]);
+        assert_eq!(results.iter().map(|x| x.score).collect::<Vec<u32>>(), vec![10, 5, 1]);
+        assert_eq!(titles(&index.search("rust", 2)), vec!["Rust", "Web server"]);
+    }
+
+    #[test]
+    fn search_matches_cjk_bigrams() {
+        let index = SearchIndex::new(&[
+            content("静态博客生成器", &[], "<p>用 Rust 写的</p>"),
+            content("博客", &[], ""),
+        ]);
+        assert_eq!(titles(&index.search("静态博客", 10)), vec!["静态博客生成器"]);
+        // The equal scores keep the order of the contents.
+        assert_eq!(titles(&index.search("博客", 10)), vec!["静态博客生成器", "博客"]);
+        assert_eq!(index.search("rust 生成", 10)[0].snippet, "用 <mark>Rust</mark> 写的");
+    }
+
+    #[test]
+    fn create_snippet_highlights_and_escapes() {
+        let tokens = vec!["rust".to_string()];
+        assert_eq!(create_snippet("a <b> RUST & \"c\"", &tokens, 120), "a &lt;b&gt; <mark>RUST</mark> &amp; &quot;c&quot;");
+        assert_eq!(create_snippet("no match here", &tokens, 5), "no ma...");
+        assert_eq!(create_snippet("", &tokens, 5), "");
+    }
+
+    #[test]
+    fn create_snippet_cuts_cjk_text_at_the_chars() {
+        let tokens = vec!["博客".to_string()];
+        let text = format!("{}博客{}", "一".repeat(60), "二".repeat(60));
+        let expected = format!("...{}<mark>博客</mark>{}...", "一".repeat(5), "二".repeat(3));
+        assert_eq!(create_snippet(&text, &tokens, 10), expected);
+
+        let text = format!("{}博客", "一".repeat(60));
+        assert_eq!(create_snippet(&text, &tokens, 10), format!("...{}<mark>博客</mark>", "一".repeat(5)));
+        assert_eq!(create_snippet(&text, &tokens, 3), "...一<mark>博客</mark>");
+        assert_eq!(create_snippet("博客一", &tokens, 1), "<mark>博</mark>...");
+    }
+}
@@ -1,19 +1,31 @@
 use std;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::io::{Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
 
 use colored::*;
-use handlebars::Handlebars;
+use handlebars::{Handlebars, TemplateFileError};
+use iron::headers::ContentType;
 use iron::prelude::*;
+use iron::Listening;
+use iron::status;
 use mount::Mount;
+use rayon::prelude::*;
+use regex::Regex;
 use serde_json::{self, Value};
 use staticfile::Static;
 
 use super::super::infrastructure::*;
+use super::build_report::milliseconds;
 pub use super::*;
 
 type Result<T> = std::result::Result<T, Error>;
+/// The files written by a template and the outputs it met.
+type Rendered = (HashMap<String, String>, HashMap<String, OutputRecord>);
 
 fn default_title() -> String {
     "<Title>".to_string()
@@ -48,6 +60,15 @@ fn default_data_directory() -> String {
 fn default_publish_directory() -> String {
     "publish".to_string()
 }
+fn default_archetype_directory() -> String {
+    "archetypes".to_string()
+}
+fn default_static_directory() -> String {
+    "static".to_string()
+}
+fn default_minify() -> bool {
+    false
+}
 fn default_root() -> String {
     ".".to_string()
 }
@@ -76,10 +97,46 @@ pub struct Site {
     pub build_directory: String,
     #[serde(default = "default_publish_directory")]
     pub publish_directory: String,
+    /// Directory of the archetypes used by `ims content new`.
+    #[serde(default = "default_archetype_directory")]
+    pub archetype_directory: String,
+    /// Files copied to the build directory as they are, e.g. the images of the contents.
+    #[serde(default = "default_static_directory")]
+    pub static_directory: String,
+    #[serde(default = "default_minify")]
+    pub minify: bool,
+    /// Output formats of each kind of content.
+    #[serde(default)]
+    pub outputs: BTreeMap<String, Vec<OutputFormat>>,
+    /// The search index for the themes.
+    #[serde(default)]
+    pub search: SearchConfig,
     #[serde(skip_serializing, default = "default_root")]
     pub root: String,
 }
 
+/// Where a site is loaded from, given by `--source` and `--config`.
+#[derive(Debug, Clone)]
+pub struct SiteSource {
+    /// The root directory of the site.
+    pub root: String,
+    /// The config file, `site.json` in the root if none.
+    pub config: Option<String>,
+}
+
+impl SiteSource {
+    pub fn config_path(&self) -> PathBuf {
+        return match self.config {
+            Some(ref config) => PathBuf::from(config),
+            None => Path::new(&self.root).join("site.json"),
+        };
+    }
+
+    pub fn load(&self) -> Result<Site> {
+        return Site::load_with_config(&self.root, &self.config_path());
+    }
+}
+
 impl Site {
     pub fn get_theme_path(&self) -> Result<String> {
         let path = Path::new(&self.root)
@@ -116,6 +173,22 @@ impl Site {
             .to_string();
         return Ok(path);
     }
+    pub fn get_archetype_path(&self) -> Result<String> {
+        let path = Path::new(&self.root).join(&self.archetype_directory);
+        let path = path
+            .to_str()
+            .ok_or(Error::new("Failed to get archetype path."))?
+            .to_string();
+        return Ok(path);
+    }
+    pub fn get_static_path(&self) -> Result<String> {
+        let path = Path::new(&self.root).join(&self.static_directory);
+        let path = path
+            .to_str()
+            .ok_or(Error::new("Failed to get static path."))?
+            .to_string();
+        return Ok(path);
+    }
     pub fn get_publish_path(&self) -> Result<String> {
         let path = Path::new(&self.root).join(&self.publish_directory);
         let path = path
@@ -139,54 +212,63 @@ impl Site {
                 .recursive(true)
                 .create(path)
                 .map_err(|err| {
-                    Error::new("Failed to create the site directory.").with_inner_error(&err)
+                    Error::new("Failed to create the site directory.").with_source(err)
                 })?;
         }
         let config_path = path.join("site.json");
         if config_path.exists() && config_path.is_file() {
             return Err(Error::new(
                 "Failed to create a new site. because a site exists in the current directory.",
-            ));
+            ).with_kind(ErrorKind::Config));
         }
         let mut site = serde_json::from_str::<Site>("{}").unwrap();
         site.root = root_path.to_string();
         let mut config_file = fs::File::create(config_path).map_err(|err| {
-            Error::new("An error occurred while creating the config file.").with_inner_error(&err)
+            Error::new("An error occurred while creating the config file.").with_source(err)
         })?;
         let content = serde_json::to_string_pretty(&site).map_err(|err| {
             Error::new("An error occurred while converting the config content.")
-                .with_inner_error(&err)
+                .with_source(err)
         })?;
         config_file.write(&content.into_bytes()).map_err(|err| {
-            Error::new("An error occurred while writing the config file.").with_inner_error(&err)
+            Error::new("An error occurred while writing the config file.").with_source(err)
         })?;
 
         return Ok(site);
     }
 
-    /// Load site config from the specified directory.
-    pub fn load(root_path: &str) -> Result<Site> {
+    /// Load the site in the directory, with the config file which may be in another place.
+    pub fn load_with_config(root_path: &str, config_path: &Path) -> Result<Site> {
         trace!("Loading site {0}", root_path);
         let path = Path::new(root_path);
     
         if !path.exists() {
-            return Err(Error::new("The dircetory is not exists."));
+            return Err(Error::new("The dircetory is not exists.")
+                .with_kind(ErrorKind::Config)
+                .with_path(root_path));
         }
-        let config_path = path.join("site.json");
+        let config_name = config_path.to_string_lossy().to_string();
         if !config_path.exists() {
-            return Err(Error::new("The config file is not exists."));
+            return Err(Error::new("The config file is not exists.")
+                .with_kind(ErrorKind::Config)
+                .with_path(&config_name));
         }
 
         let mut config_file = fs::File::open(config_path).map_err(|error| {
-            Error::new("Failed to open the config file.").with_inner_error(&error)
+            Error::new("Failed to open the config file.").with_source(error)
         })?;
         let mut buffer = String::new();
         config_file.read_to_string(&mut buffer).map_err(|error| {
-            Error::new("Failed to read the config file.").with_inner_error(&error)
+            Error::new("Failed to read the config file.").with_source(error)
         })?;
 
         let mut site = serde_json::from_str::<Site>(&buffer).map_err(|error| {
-            Error::new("Failed to resolve the config file.").with_inner_error(&error)
+            let (line, column) = (error.line(), error.column());
+            Error::new("Failed to resolve the config file.")
+                .with_kind(ErrorKind::Config)
+                .with_path(&config_name)
+                .with_position(line, column)
+                .with_source(error)
         })?;
         site.root = root_path.to_string();
         return Ok(site);
@@ -201,64 +283,201 @@ impl Site {
         return Ok(());
     }
 
-    pub fn build(&self) -> Result<()> {
-        println!(
+    /// Build the site, only the outputs whose inputs changed since the last build are written.
+    ///
+    /// Use `clean` to clear the build directory and render everything again.
+    /// With `strict`, nothing is written if any content or template fails, and all the
+    /// failures are returned in the error.
+    pub fn build(&self, clean: bool, strict: bool) -> Result<BuildReport> {
+        // On the stderr, so that the stdout only has the json report with `--report json`.
+        eprintln!(
             "{0:>12} {1} {2}",
             "Building".green().bold(),
             "site",
             self.root
         );
-        let model = self.create_model()?;
+        let start = Instant::now();
+        let mut report = BuildReport::new();
+        let contents = self.load_contents(&mut report)?;
+        let model = self.create_model(&contents, &mut report)?;
+        report.timings.loading = milliseconds(start.elapsed());
         let build_path = self.get_build_path()?;
         let data_path = Path::new(&build_path);
-        if data_path.exists() {
+        let cache_path = data_path.join(BUILD_CACHE_FILE);
+        let previous = if clean {
+            BuildCache::new()
+        } else {
+            BuildCache::load(&cache_path)
+        };
+        if clean && data_path.exists() {
             fs::remove_dir_all(&data_path).map_err(|error| {
-                Error::new("Failed to clear data directory.").with_inner_error(&error)
+                Error::new("Failed to clear data directory.").with_source(error)
             })?;
         }
-        let mut render = self.create_render()?;
-        for (key, _) in render.get_templates().clone().iter() {
-            trace!("Rendering template:{}", key);
-            let map = match render.render_with_file(key, &model) {
-                Ok(map) => map,
+        let mut cache = BuildCache::new();
+        let previous_outputs: HashMap<String, String> = previous
+            .outputs
+            .iter()
+            .filter(|&(name, _)| data_path.join(name).exists())
+            .map(|(name, record)| (name.clone(), record.fingerprint.clone()))
+            .collect();
+        let digest = self.create_digest(&model)?;
+        let dependencies = self.create_template_dependencies()?;
+        let step = Instant::now();
+        let formats: Vec<(&String, &OutputFormat)> = self
+            .outputs
+            .iter()
+            .flat_map(|(kind, list)| list.iter().map(move |format| (kind, format)))
+            .collect();
+        // The templates are parsed once, the render engine is shared by all the threads.
+        let render = self.create_render()?;
+        // The templates of the output formats are rendered for each content only.
+        let mut templates: Vec<String> = render
+            .get_templates()
+            .keys()
+            .filter(|key| !formats.iter().any(|&(_, format)| format.template.as_ref() == Some(*key)))
+            .cloned()
+            .collect();
+        templates.sort();
+        // Every template is rendered with its own `file` helper, so they can run in parallel.
+        let mut results: Vec<(String, Result<Rendered>)> = templates
+            .par_iter()
+            .map(|key| {
+                let (seed, partials) = match dependencies.get(key) {
+                    Some(&(ref hash, ref partials)) => (format!("{}{}", digest, hash), partials.clone()),
+                    None => (format!("{}{}", digest, key), vec![]),
+                };
+                let helper = IncrementalFileHelper::new(&seed, key, partials, previous_outputs.clone());
+                (key.clone(), self.render_template(&render, key, &model, helper))
+            }).collect();
+        results.par_extend(formats.par_iter().map(|&(kind, format)| {
+            let result = self.render_format(&render, kind, format, &model, &dependencies, &previous_outputs);
+            (format.key(kind), result)
+        }));
+        if self.search.enabled {
+            results.push(("search".to_string(), self.render_search_index(&contents, &previous_outputs)));
+        }
+        let mut files: Vec<(String, String)> = vec![];
+        let mut collisions: Vec<String> = vec![];
+        for (key, result) in results {
+            let (map, outputs) = match result {
+                Ok(result) => result,
                 Err(error) => {
                     warn!("{}", error);
+                    report.failed.push(ReportItem::from_error(&key, &error));
+                    // Keep the outputs of the last build, they are not stale.
+                    for (name, record) in previous.outputs.iter() {
+                        if record.template == key {
+                            cache.outputs.insert(name.clone(), record.clone());
+                        }
+                    }
                     continue;
                 }
             };
-            for (name, content) in map.iter() {
-                trace!("Saving:{}", name);
-                let file_path = data_path.clone().join(name);
-                {
-                    let parent_path = file_path
-                        .parent()
-                        .ok_or(Error::new("Failed to get parent directory."))?;
-                    fs::DirBuilder::new()
-                        .recursive(true)
-                        .create(parent_path)
-                        .map_err(|error| {
-                            Error::new("An error occurred while creating the parent directory.")
-                                .with_inner_error(&error)
-                        })?;
+            let mut map = map;
+            for (name, record) in outputs {
+                // A file written by two templates fails the build, whichever is kept is wrong.
+                if let Some(other) = cache.outputs.get(&name) {
+                    if other.template != key {
+                        collisions.push(format!(
+                            "\n    {}: written by both template {} and {}",
+                            name, other.template, key
+                        ));
+                        map.remove(&name);
+                        continue;
+                    }
+                }
+                cache.outputs.insert(name, record);
+            }
+            let mut map: Vec<(String, String)> = map.into_iter().collect();
+            map.sort();
+            files.extend(map);
+        }
+        report.pages = cache.outputs.len();
+        report.timings.rendering = milliseconds(step.elapsed());
+        if !collisions.is_empty() {
+            let message = format!(
+                "Found {} output(s) written by more than one template.{}",
+                collisions.len(),
+                collisions.concat()
+            );
+            return Err(Error::new(&message).with_kind(ErrorKind::Build));
+        }
+        if strict && !report.failed.is_empty() {
+            let mut message = format!("Build failed with {} error(s).", report.failed.len());
+            for item in &report.failed {
+                message.push_str(&format!("\n    {}: {}", item.path, item.reason));
+            }
+            return Err(Error::new(&message).with_kind(ErrorKind::Build));
+        }
+
+        let step = Instant::now();
+        // The names are unique, as the collisions between templates fail the build above.
+        let sizes = files
+            .par_iter()
+            .map(|&(ref name, ref content)| self.save_file(data_path, name, content))
+            .collect::<Result<Vec<u64>>>()?;
+        report.files_written = sizes.len();
+        report.bytes_written = sizes.iter().sum();
+        report.files_unchanged = report.pages.saturating_sub(files.len());
+        report.timings.writing = milliseconds(step.elapsed());
+
+        let step = Instant::now();
+        let theme_path = self.get_theme_path()?;
+        let static_path = self.get_static_path()?;
+        let static_path = Path::new(&static_path);
+        let copied = Cell::new(0);
+        let assets = RefCell::new(HashMap::new());
+        for &(source_path, is_theme) in [(Path::new(&theme_path), true), (static_path, false)].iter() {
+            if !is_theme && !source_path.is_dir() {
+                continue;
+            }
+            copy_all_file(source_path, &data_path, |source, target| {
+                // The static files of the site win over the files of the theme.
+                if is_theme && (source.starts_with("layout") || static_path.join(source).is_file()) {
+                    return false;
+                }
+                let name = source.to_string_lossy().to_string();
+                let hash = match hash_file(&source_path.join(source)) {
+                    Ok(hash) => hash,
+                    Err(_) => return true,
+                };
+                let unchanged = target.exists() && previous.assets.get(&name) == Some(&hash);
+                assets.borrow_mut().insert(name, hash);
+                if unchanged {
+                    return false;
                 }
+                trace!("Copying file from {:?} to {:?}", source, target);
+                copied.set(copied.get() + 1);
+                return true;
+            })?;
+        }
+        cache.assets = assets.into_inner();
+        report.assets_copied = copied.get();
 
-                let mut file = fs::File::create(file_path).map_err(|error| {
-                    Error::new("An error occurred while creating the file.")
-                        .with_inner_error(&error)
+        let stale = previous
+            .outputs
+            .keys()
+            .filter(|name| !cache.outputs.contains_key(*name))
+            .chain(
+                previous
+                    .assets
+                    .keys()
+                    .filter(|name| !cache.assets.contains_key(*name)),
+            );
+        for name in stale {
+            let file_path = data_path.join(name);
+            if file_path.is_file() {
+                trace!("Removing stale file:{:?}", file_path);
+                fs::remove_file(&file_path).map_err(|error| {
+                    Error::new("Failed to remove the stale file.").with_source(error)
                 })?;
-                file.write_all(&mut content.clone().into_bytes())
-                    .map_err(|err| {
-                        Error::new("An error occurred while save the file.").with_inner_error(&err)
-                    })?;
             }
         }
-        let theme_path = self.get_theme_path()?;
-        let theme_path = Path::new(&theme_path);
-        copy_all_file(&theme_path, &data_path, |source, target| {
-            trace!("Copying file from {:?} to {:?}", source, target);
-            return !source.starts_with("layout");
-        })?;
-        return Ok(());
+        cache.save(&cache_path)?;
+        report.timings.copying = milliseconds(step.elapsed());
+        report.timings.total = milliseconds(start.elapsed());
+        return Ok(report);
     }
 
     pub fn publish(&self) -> Result<()> {
@@ -273,30 +492,64 @@ impl Site {
         let publish_path = self.get_publish_path()?;
         let publish_path = Path::new(&publish_path);
         copy_all_file(&build_path, &publish_path, |source, target| {
+            if source == Path::new(BUILD_CACHE_FILE) {
+                return false;
+            }
             trace!("Copying file from {:?} to {:?}", source, target);
             return true;
         })?;
         return Ok(());
     }
 
+    /// Serve the build directory, and search the contents on `/__ims/search?q=`.
     pub fn server(&self, port: u64) -> Result<()> {
-        self.build()?;
-        let mut mount = Mount::new();
-        mount.mount("/", Static::new(Path::new(&self.root).join("build")));
+        self.build(false, false)?.print();
         let address = format!("127.0.0.1:{}", port);
         println!("{0:>12} http://{1}", "Running".green().bold(), address);
-        Iron::new(mount)
-            .http(address)
-            .map_err(|error| Error::new("Failed to lanuch server.").with_inner_error(&error))?;
+        // Dropping the listening server waits until the server stops.
+        let _listening = self.start_server(port)?;
         return Ok(());
     }
 
+    /// Serve the build directory and the search in the background, the site must be built.
+    pub fn start_server(&self, port: u64) -> Result<Listening> {
+        let contents = self.load_contents(&mut BuildReport::new())?;
+        let index = Arc::new(SearchIndex::new(&contents));
+        let mut mount = Mount::new();
+        mount.mount("/", Static::new(self.get_build_path()?));
+        mount.mount("/__ims/search", move |request: &mut Request| {
+            let mut query = String::new();
+            let mut limit = 20;
+            for (key, value) in request.url.as_ref().query_pairs() {
+                match key.as_ref() {
+                    "q" => query = value.to_string(),
+                    "limit" => limit = value.parse().unwrap_or(limit),
+                    _ => {}
+                }
+            }
+            let results = index.search(&query, limit);
+            let data = json!({ "query": query, "total": results.len(), "results": results });
+            let mut response = Response::with((status::Ok, data.to_string()));
+            response.headers.set(ContentType::json());
+            return Ok(response);
+        });
+        let address = format!("127.0.0.1:{}", port);
+        let listening = Iron::new(mount)
+            .http(address)
+            .map_err(|error| {
+                Error::new("Failed to lanuch server.")
+                    .with_kind(ErrorKind::Server)
+                    .with_source(error)
+            })?;
+        return Ok(listening);
+    }
+
     fn create_render(&self) -> Result<Handlebars> {
         trace!("Creating render engine");
         let theme_path = self.get_theme_path()?;
         let layout_path = Path::new(&theme_path);
         let templates = get_all_file(&layout_path)
-            .map_err(|err| Error::new("Failed to find template files.").with_inner_error(&err))?;
+            .map_err(|err| Error::new("Failed to find template files.").with_source(err))?;
 
         let mut render = Handlebars::new();
         render.register_helper("json", Box::new(json_helper));
@@ -325,23 +578,276 @@ impl Site {
                         .to_str()
                         .ok_or(Error::new("Format of \"path\" is incorrect ."))?,
                 ).map_err(|err| {
-                    Error::new("Failed to get the template name.").with_inner_error(&err)
+                    Error::new("Failed to get the template name.").with_source(err)
                 })?.to_str()
                 .ok_or(Error::new("Format of \"path\" is incorrect ."))?;
             trace!("Registering template:{}", name);
             render.register_template_file(name, &path).map_err(|err| {
-                Error::new("Failed to register the template.").with_inner_error(&err)
+                let mut error = Error::new("Failed to register the template.")
+                    .with_kind(ErrorKind::Template)
+                    .with_path(template);
+                if let TemplateFileError::TemplateError(ref template_error) = err {
+                    if let (Some(line), Some(column)) =
+                        (template_error.line_no, template_error.column_no)
+                    {
+                        error = error.with_position(line, column);
+                    }
+                }
+                error.with_source(err)
             })?;
         }
         return Ok(render);
     }
 
-    fn create_model(&self) -> Result<Value> {
-        trace!("Creating render model");
-        let mut contents = Content::load_all(&self)?;
+    /// Render a template with the `file` helper, get the files and the outputs it met.
+    fn render_template(
+        &self,
+        render: &Handlebars,
+        key: &str,
+        model: &Value,
+        helper: IncrementalFileHelper,
+    ) -> Result<Rendered> {
+        trace!("Rendering template:{}", key);
+        let outputs = helper.outputs();
+        let map = render.render_shared_with_file(key, model, Box::new(helper)).map_err(|err| {
+            let mut error = Error::new(&format!("Failed to render the template:{}.", key))
+                .with_kind(ErrorKind::Template)
+                .with_path(key);
+            if let (Some(line), Some(column)) = (err.line_no, err.column_no) {
+                error = error.with_position(line, column);
+            }
+            error.with_source(err)
+        })?;
+        let outputs = outputs
+            .lock()
+            .map_err(|_| Error::new("Failed to get the outputs of the template."))?
+            .clone();
+        return Ok((map, outputs));
+    }
+
+    /// Write an output format for each content of the kind, the unchanged ones are skipped.
+    fn render_format(
+        &self,
+        render: &Handlebars,
+        kind: &str,
+        format: &OutputFormat,
+        model: &Value,
+        dependencies: &HashMap<String, (String, Vec<String>)>,
+        previous: &HashMap<String, String>,
+    ) -> Result<Rendered> {
+        let key = format.key(kind);
+        trace!("Rendering output format:{}", key);
+        let config_error = |message: &str| {
+            Error::new(message)
+                .with_kind(ErrorKind::Config)
+                .with_path(&key)
+        };
+        let (template, seed, partials) = match format.template {
+            _ if format.is_json() => (None, String::new(), vec![]),
+            Some(ref template) => match dependencies.get(template) {
+                Some(&(ref hash, ref partials)) => (Some(template), hash.clone(), partials.clone()),
+                None => return Err(config_error(&format!("The template {} is not exists.", template))),
+            },
+            None => return Err(config_error("The template of the output format is missing.")),
+        };
+        let seed = format!("{}\n{}\n{}", key, seed, model["site"]);
+        let mut map = HashMap::new();
+        let mut outputs = HashMap::new();
+        let contents = model["contents"].as_array().cloned().unwrap_or_default();
+        for content in contents.iter().filter(|x| x["kind"] == kind) {
+            let id = content["id"].as_str().unwrap_or_default();
+            let name = format
+                .get_path(id)
+                .ok_or(config_error("The directory of the output format is outside the build directory."))?;
+            let fingerprint = hash_bytes(format!("{}\n{}\n{}", seed, name, content).as_bytes());
+            let unchanged = previous.get(&name) == Some(&fingerprint);
+            outputs.insert(
+                name.clone(),
+                OutputRecord {
+                    fingerprint: fingerprint,
+                    template: key.clone(),
+                    partials: partials.clone(),
+                    content: Some(id.to_string()),
+                },
+            );
+            if unchanged {
+                continue;
+            }
+            let data = match template {
+                Some(template) => {
+                    let data = json!({ "site": model["site"], "content": content });
+                    // Only html needs escaping, e.g. a plain text output should keep `<`.
+                    let result = if ["html", "htm"].contains(&format.get_extension()) {
+                        render.render(template, &data)
+                    } else {
+                        render.render_unescaped(template, &data)
+                    };
+                    result.map_err(|err| {
+                        let mut error = Error::new(&format!("Failed to render the template:{}.", template))
+                            .with_kind(ErrorKind::Template)
+                            .with_path(template);
+                        if let (Some(line), Some(column)) = (err.line_no, err.column_no) {
+                            error = error.with_position(line, column);
+                        }
+                        error.with_source(err)
+                    })?
+                }
+                None => serde_json::to_string(content).map_err(|err| {
+                    Error::new("Failed to serialize the content.").with_source(err)
+                })?,
+            };
+            map.insert(name, data);
+        }
+        return Ok((map, outputs));
+    }
+
+    /// Write the search index of the contents, the unchanged files are skipped.
+    fn render_search_index(&self, contents: &[Content], previous: &HashMap<String, String>) -> Result<Rendered> {
+        trace!("Creating search index");
+        let mut map = HashMap::new();
+        let mut outputs = HashMap::new();
+        for (name, data) in SearchIndex::new(contents).to_files(&self.search)? {
+            let fingerprint = hash_bytes(data.as_bytes());
+            if previous.get(&name) != Some(&fingerprint) {
+                map.insert(name.clone(), data);
+            }
+            let record = OutputRecord {
+                fingerprint: fingerprint,
+                template: "search".to_string(),
+                partials: vec![],
+                content: None,
+            };
+            outputs.insert(name, record);
+        }
+        return Ok((map, outputs));
+    }
+
+    /// Save a rendered file, get the number of bytes written.
+    fn save_file(&self, data_path: &Path, name: &str, content: &str) -> Result<u64> {
+        trace!("Saving:{}", name);
+        let file_path = data_path.join(name);
+        {
+            let parent_path = file_path
+                .parent()
+                .ok_or(Error::new("Failed to get parent directory."))?;
+            fs::DirBuilder::new()
+                .recursive(true)
+                .create(parent_path)
+                .map_err(|error| {
+                    Error::new("An error occurred while creating the parent directory.")
+                        .with_source(error)
+                })?;
+        }
+
+        let content = if self.minify {
+            minify_file(name, content)
+        } else {
+            content.to_string()
+        };
+        let mut file = fs::File::create(file_path).map_err(|error| {
+            Error::new("An error occurred while creating the file.").with_source(error)
+        })?;
+        let size = content.len() as u64;
+        file.write_all(&mut content.into_bytes()).map_err(|err| {
+            Error::new("An error occurred while save the file.").with_source(err)
+        })?;
+        return Ok(size);
+    }
+
+    /// Hash of the site config and the front matter of all contents.
+    ///
+    /// Any output may list the contents, so all of them are re-rendered when it changes.
+    fn create_digest(&self, model: &Value) -> Result<String> {
+        let mut contents = model["contents"].clone();
+        if let Some(list) = contents.as_array_mut() {
+            for item in list.iter_mut() {
+                if let Some(map) = item.as_object_mut() {
+                    map.remove("content");
+                    map.remove("html");
+                    map.remove("toc");
+                }
+            }
+        }
+        let data = serde_json::to_string(&json!({
+            "site": model["site"],
+            "contents": contents
+        })).map_err(|err| Error::new("Failed to create the digest.").with_source(err))?;
+        return Ok(hash_bytes(&data.into_bytes()));
+    }
+
+    /// Get the hash of each template including its partials, and the partials it uses.
+    fn create_template_dependencies(&self) -> Result<HashMap<String, (String, Vec<String>)>> {
+        let theme_path = self.get_theme_path()?;
+        let layout_path = Path::new(&theme_path);
+        let re = Regex::new(r"\{\{~?#?>\s*([^\s}~]+)").map_err(|err| {
+            Error::new("An error occurred while resolving the templates.").with_source(err)
+        })?;
+        let mut sources: HashMap<String, (String, Vec<String>)> = HashMap::new();
+        for template in &get_all_file(&layout_path)? {
+            let path = Path::new(template);
+            if path.extension().map(|x| x != "hbs").unwrap_or(true) {
+                continue;
+            }
+            let name = path
+                .strip_prefix(&layout_path)
+                .map_err(|err| {
+                    Error::new("Failed to get the template name.").with_source(err)
+                })?.to_string_lossy()
+                .to_string();
+            let mut buffer = String::new();
+            fs::File::open(path)
+                .and_then(|mut file| file.read_to_string(&mut buffer))
+                .map_err(|err| Error::new("Failed to read the template.").with_source(err))?;
+            let partials = re
+                .captures_iter(&buffer)
+                .map(|caps| caps[1].to_string())
+                .collect();
+            sources.insert(name, (hash_bytes(buffer.as_bytes()), partials));
+        }
+        let mut dependencies = HashMap::new();
+        for name in sources.keys() {
+            let mut partials: Vec<String> = vec![];
+            let mut pending = sources[name].1.clone();
+            while let Some(partial) = pending.pop() {
+                if partials.contains(&partial) || &partial == name {
+                    continue;
+                }
+                if let Some(&(_, ref children)) = sources.get(&partial) {
+                    pending.extend(children.iter().cloned());
+                }
+                partials.push(partial);
+            }
+            partials.sort();
+            let mut data = sources[name].0.clone();
+            for partial in &partials {
+                if let Some(&(ref hash, _)) = sources.get(partial) {
+                    data.push_str(hash);
+                }
+            }
+            dependencies.insert(name.clone(), (hash_bytes(data.as_bytes()), partials));
+        }
+        return Ok(dependencies);
+    }
+
+    /// Load the published contents with the markdown rendered, the newest first.
+    fn load_contents(&self, report: &mut BuildReport) -> Result<Vec<Content>> {
+        let mut contents = Content::load_all_into(&self, report)?;
+        contents.par_iter_mut().for_each(|content| content.render());
         contents.sort_by(|a, b| b.create_time.cmp(&a.create_time));
+        return Ok(contents);
+    }
+
+    /// Get the model rendered by the templates, it has the published contents.
+    pub fn render_model(&self) -> Result<Value> {
+        let mut report = BuildReport::new();
+        let contents = self.load_contents(&mut report)?;
+        return self.create_model(&contents, &mut report);
+    }
+
+    fn create_model(&self, contents: &[Content], report: &mut BuildReport) -> Result<Value> {
+        trace!("Creating render model");
         let mut tags: Vec<ItemGroup<&Content>> = vec![];
-        for content in &contents {
+        for content in contents {
             for tag in &content.tags {
                 let index = tags.iter().position(|x| x.name == tag.to_string());
                 if let Some(index) = index {
@@ -354,9 +860,10 @@ impl Site {
             }
         }
         tags.sort_by(|a, b| b.list.len().cmp(&a.list.len()));
+        report.tags = tags.len();
 
         let mut archives: Vec<ItemGroup<&Content>> = vec![];
-        for content in &contents {
+        for content in contents {
             
             let date =  content.create_time.format("%b %Y").to_string();
 
@@ -373,9 +880,37 @@ impl Site {
 
         return Ok(json!({
             "site":self.clone(),
-            "contents":&contents,
+            "contents":contents,
             "tags":tags,
             "archives":archives
         }));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    #[test]
+    fn build_fails_if_two_templates_write_a_file() {
+        let root = env::temp_dir().join(format!("ims-test-collision-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let theme = root.join("theme/default");
+        fs::create_dir_all(&theme).unwrap();
+        fs::create_dir_all(root.join("content")).unwrap();
+        fs::write(theme.join("a.hbs"), "{{#file \"./index.html\"}}a{{/file}}").unwrap();
+        fs::write(theme.join("b.hbs"), "{{#file \"post/../index.html\"}}b{{/file}}").unwrap();
+        let mut site = serde_json::from_str::<Site>("{}").unwrap();
+        site.root = root.to_string_lossy().to_string();
+
+        let result = site.build(true, false);
+        let written = root.join("build/index.html").exists();
+        fs::remove_dir_all(&root).unwrap();
+        let error = result.unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Build);
+        assert!(error.message.contains("index.html: written by both template a.hbs and b.hbs"));
+        assert!(!written);
+    }
+}
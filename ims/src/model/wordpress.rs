@@ -0,0 +1,443 @@
+use std;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+use regex::{Captures, Regex};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+use xml::common::Position;
+use xml::reader::{EventReader, XmlEvent};
+
+use super::super::infrastructure::*;
+use super::importer::parse_any_date;
+use super::{Diagnostic, Importer};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// An `item` of a WordPress export (WXR), i.e. a post, a page or an attachment.
+#[derive(Debug, Default)]
+struct Item {
+    title: String,
+    link: String,
+    pub_date: String,
+    content: String,
+    excerpt: String,
+    post_id: String,
+    post_date: String,
+    post_date_gmt: String,
+    post_name: String,
+    status: String,
+    post_type: String,
+    attachment_url: String,
+    tags: Vec<String>,
+    categories: Vec<String>,
+    meta: BTreeMap<String, String>,
+}
+
+impl Item {
+    /// Set the field of the element, the name has the namespace prefix, e.g. `wp:status`.
+    fn set(&mut self, name: &str, domain: &str, text: String) {
+        match name {
+            "title" => self.title = text,
+            "link" => self.link = text,
+            "pubDate" => self.pub_date = text,
+            "content:encoded" => self.content = text,
+            "excerpt:encoded" => self.excerpt = text,
+            "wp:post_id" => self.post_id = text,
+            "wp:post_date" => self.post_date = text,
+            "wp:post_date_gmt" => self.post_date_gmt = text,
+            "wp:post_name" => self.post_name = text,
+            "wp:status" => self.status = text,
+            "wp:post_type" => self.post_type = text,
+            "wp:attachment_url" => self.attachment_url = text,
+            "category" if domain == "post_tag" => self.tags.push(text),
+            "category" if domain == "category" => self.categories.push(text),
+            _ => {}
+        }
+    }
+
+    /// Get the create time, the local `post_date` is used as UTC if there is no GMT date.
+    fn date(&self) -> Option<DateTime<Utc>> {
+        // The GMT date of the drafts is `0000-00-00 00:00:00`.
+        return [&self.post_date_gmt, &self.post_date, &self.pub_date]
+            .iter()
+            .filter(|x| !x.is_empty() && !x.starts_with("0000"))
+            .filter_map(|x| parse_any_date(x))
+            .next();
+    }
+}
+
+/// Read the items of the export.
+fn parse_items(text: &str) -> Result<Vec<Item>> {
+    let mut items = vec![];
+    let mut item: Option<Item> = None;
+    let mut buffer = String::new();
+    let mut domain = String::new();
+    let mut meta_key = String::new();
+    for event in EventReader::from_str(text) {
+        let event = event.map_err(|err| {
+            let position = err.position();
+            Error::new("Failed to parse the WordPress export.")
+                .with_kind(ErrorKind::FrontMatter)
+                .with_position(position.row as usize + 1, position.column as usize + 1)
+                .with_source(err)
+        })?;
+        match event {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                buffer.clear();
+                if name.local_name == "item" && name.prefix.is_none() {
+                    item = Some(Item::default());
+                } else if name.local_name == "category" {
+                    domain = attributes
+                        .iter()
+                        .find(|x| x.name.local_name == "domain")
+                        .map(|x| x.value.clone())
+                        .unwrap_or_default();
+                }
+            }
+            XmlEvent::Characters(text) | XmlEvent::CData(text) | XmlEvent::Whitespace(text) => {
+                buffer.push_str(&text)
+            }
+            XmlEvent::EndElement { name } => {
+                let name = match name.prefix {
+                    Some(ref prefix) => format!("{}:{}", prefix, name.local_name),
+                    None => name.local_name.clone(),
+                };
+                let text = buffer.trim().to_string();
+                buffer.clear();
+                match (name.as_str(), item.as_mut()) {
+                    ("item", Some(_)) => items.push(item.take().unwrap()),
+                    ("wp:meta_key", Some(_)) => meta_key = text,
+                    ("wp:meta_value", Some(item)) => {
+                        item.meta.insert(meta_key.clone(), text);
+                    }
+                    (name, Some(item)) => item.set(name, &domain, text),
+                    (_, None) => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    return Ok(items);
+}
+
+lazy_static! {
+    static ref BLOCK_COMMENT: Regex = Regex::new(r"(?s)<!--\s*/?wp:.*?-->").unwrap();
+    static ref PRE: Regex =
+        Regex::new(r"(?is)<pre[^>]*>(?:\s*<code[^>]*>)?(.*?)(?:</code>\s*)?</pre>").unwrap();
+    static ref ATTRIBUTE: Regex = Regex::new(r#"\s([\w:-]+)\s*=\s*["']([^"']*)["']"#).unwrap();
+    static ref IMG: Regex = Regex::new(r"(?i)<img\s[^>]*>").unwrap();
+    static ref LINK: Regex = Regex::new(r"(?is)(<a\s[^>]*>)(.*?)</a>").unwrap();
+    static ref HEADER: Regex = Regex::new(r"(?is)<h([1-6])[^>]*>(.*?)</h[1-6]>").unwrap();
+    static ref BLOCKQUOTE: Regex = Regex::new(r"(?is)<blockquote[^>]*>(.*?)</blockquote>").unwrap();
+    static ref REPLACES: Vec<(Regex, &'static str)> = [
+        (r"(?is)<(?:strong|b)>(.*?)</(?:strong|b)>", "**$1**"),
+        (r"(?is)<(?:em|i)>(.*?)</(?:em|i)>", "*$1*"),
+        (r"(?is)<code>(.*?)</code>", "`$1`"),
+        (r"(?is)<li[^>]*>(.*?)</li>", "\n- $1"),
+        (r"(?i)</?(?:ul|ol)[^>]*>", "\n\n"),
+        (r"(?i)<br\s*/?>", "  \n"),
+        (r"(?is)<p[^>]*>(.*?)</p>", "\n\n$1\n\n"),
+    ].iter()
+        .map(|&(pattern, replace)| (Regex::new(pattern).unwrap(), replace))
+        .collect();
+    static ref BLANK_LINES: Regex = Regex::new(r"\n[ \t]*\n(?:[ \t]*\n)+").unwrap();
+    static ref CODE_BLOCK: Regex = Regex::new(r"\n*\u{0}(\d+)\u{0}\n*").unwrap();
+    static ref UPLOAD_URL: Regex =
+        Regex::new(r#"(?:(?:https?:)?//[^/\s"'<>()]+)?/wp-content/uploads/[^\s"'<>()?#]+"#).unwrap();
+}
+
+/// Get the value of an attribute of a html tag, the name is case insensitive.
+fn attribute(tag: &str, name: &str) -> String {
+    return ATTRIBUTE
+        .captures_iter(tag)
+        .find(|x| x[1].eq_ignore_ascii_case(name))
+        .map(|x| x[2].to_string())
+        .unwrap_or_default();
+}
+
+/// Convert the common tags of the html to markdown, the other tags are kept as html.
+fn html_to_markdown(html: &str) -> String {
+    let mut text = BLOCK_COMMENT.replace_all(html, "").to_string();
+    // The code blocks are put back after the other tags are converted.
+    let mut blocks = vec![];
+    text = PRE
+        .replace_all(&text, |caps: &Captures| {
+            let code = caps[1]
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&amp;", "&");
+            blocks.push(format!("\n\n```\n{}\n```\n\n", code.trim_matches('\n')));
+            format!("\u{0}{}\u{0}", blocks.len() - 1)
+        })
+        .to_string();
+    text = IMG
+        .replace_all(&text, |caps: &Captures| {
+            format!("![{}]({})", attribute(&caps[0], "alt"), attribute(&caps[0], "src"))
+        })
+        .to_string();
+    text = LINK
+        .replace_all(&text, |caps: &Captures| {
+            format!("[{}]({})", &caps[2], attribute(&caps[1], "href"))
+        })
+        .to_string();
+    text = HEADER
+        .replace_all(&text, |caps: &Captures| {
+            let level: usize = caps[1].parse().unwrap_or(1);
+            format!("\n\n{} {}\n\n", "#".repeat(level), caps[2].trim())
+        })
+        .to_string();
+    text = BLOCKQUOTE
+        .replace_all(&text, |caps: &Captures| {
+            let lines: Vec<String> = caps[1].trim().lines().map(|x| format!("> {}", x.trim())).collect();
+            format!("\n\n{}\n\n", lines.join("\n"))
+        })
+        .to_string();
+    for &(ref re, replace) in REPLACES.iter() {
+        text = re.replace_all(&text, replace).to_string();
+    }
+    let text = text.replace("\r\n", "\n");
+    let text = BLANK_LINES.replace_all(&text, "\n\n").to_string();
+    let text = CODE_BLOCK
+        .replace_all(&text, |caps: &Captures| blocks[caps[1].parse::<usize>().unwrap()].clone())
+        .to_string();
+    return text.trim().to_string() + "\n";
+}
+
+impl<'a> Importer<'a> {
+    /// Import the posts and pages of a WordPress export (WXR).
+    ///
+    /// The uploaded files are copied from `attachments`, a local mirror of `wp-content/uploads`
+    /// or of the whole site.
+    pub fn import_wordpress(&mut self, file: &Path, attachments: Option<&Path>) -> Result<()> {
+        let source = file.to_string_lossy().to_string();
+        let text = fs::read_to_string(file).map_err(|err| {
+            Error::new("Failed to read file.").with_path(&source).with_source(err)
+        })?;
+        let items = parse_items(&text).map_err(|err| err.with_path(&source))?;
+
+        // Copy all attachments, so that the featured images are copied as well.
+        let mut thumbnails = HashMap::new();
+        for item in items.iter().filter(|x| x.post_type == "attachment" && attachments.is_some()) {
+            if let Some(url) = self.copy_upload(&source, &item.attachment_url, attachments) {
+                thumbnails.insert(item.post_id.clone(), url);
+            }
+        }
+        let mut skipped: BTreeMap<&str, usize> = BTreeMap::new();
+        for item in &items {
+            let name = match item.post_type.as_str() {
+                "post" => "posts",
+                "page" => "pages",
+                "attachment" => continue,
+                other => {
+                    *skipped.entry(other).or_insert(0) += 1;
+                    continue;
+                }
+            };
+            let item_source = format!("{}#{}", source, item.post_id);
+            if item.status == "trash" {
+                self.diagnostics.push(Diagnostic::warning(&item_source, "The trashed item is not imported."));
+                continue;
+            }
+            let slug = if item.post_name.is_empty() { &item.post_id } else { &item.post_name };
+            let path = match self.reserve(&item_source, &format!("{}/{}.md", name, slug))? {
+                Some(path) => path,
+                None => continue,
+            };
+            let date = match item.date() {
+                Some(date) => date,
+                None => {
+                    self.diagnostics.push(Diagnostic::warning(&item_source, "No date, now is used."));
+                    Utc::now()
+                }
+            };
+            let mut meta = Map::new();
+            if !item.post_name.is_empty() {
+                meta.insert("slug".to_string(), json!(item.post_name));
+            }
+            if !item.categories.is_empty() {
+                meta.insert("categories".to_string(), json!(item.categories));
+            }
+            if !item.link.is_empty() {
+                meta.insert("link".to_string(), json!(item.link));
+            }
+            if let Some(url) = item.meta.get("_thumbnail_id").and_then(|x| thumbnails.get(x)) {
+                meta.insert("thumbnail".to_string(), json!(url));
+            }
+            let content = UPLOAD_URL
+                .replace_all(&item.content, |caps: &Captures| {
+                    self.copy_upload(&item_source, &caps[0], attachments)
+                        .unwrap_or(caps[0].to_string())
+                })
+                .to_string();
+            let mark = json!({
+                "id": Uuid::new_v4().to_string(),
+                "title": item.title,
+                "description": strip_html(&item.excerpt),
+                "target": if item.status == "publish" || item.status == "future" { "POST" } else { "DRAFT" },
+                "kind": if name == "pages" { "page" } else { "post" },
+                "tags": item.tags,
+                "create_time": date.to_rfc3339_opts(SecondsFormat::Secs, true),
+                "meta": if meta.is_empty() { Value::Null } else { Value::Object(meta) }
+            });
+            self.write_content(&item_source, &path, &mark, &html_to_markdown(&content))?;
+        }
+        for (post_type, count) in skipped {
+            self.diagnostics.push(Diagnostic::warning(
+                &source,
+                &format!("{} item(s) of the type \"{}\" are not imported.", count, post_type),
+            ));
+        }
+        return Ok(());
+    }
+
+    /// Copy an uploaded file from the mirror into `uploads` of the static directory, get
+    /// the new url.
+    fn copy_upload(&mut self, source: &str, url: &str, attachments: Option<&Path>) -> Option<String> {
+        let marker = "/wp-content/uploads/";
+        let path = match url.find(marker) {
+            Some(index) => url[index + marker.len()..].replace("%20", " "),
+            None => return None,
+        };
+        // The url comes from the export, it must not reach outside of the mirror.
+        let path = match normalize_output_path(&path) {
+            Some(path) => path,
+            None => {
+                self.diagnostics.push(Diagnostic::warning(
+                    source,
+                    &format!("The attachment {} is outside the uploads directory.", url),
+                ));
+                return None;
+            }
+        };
+        let attachments = match attachments {
+            Some(attachments) => attachments,
+            None => {
+                self.diagnostics.push(Diagnostic::warning(
+                    source,
+                    &format!("The attachment {} is not copied, use --attachments to copy it.", url),
+                ));
+                return None;
+            }
+        };
+        let files: Vec<PathBuf> = vec![
+            attachments.join(&path),
+            attachments.join("wp-content/uploads").join(&path),
+        ];
+        let target = normalize_output_path(&format!("uploads/{}", path))?;
+        let file = match files.iter().find(|x| x.is_file()) {
+            Some(file) => file,
+            None => {
+                self.diagnostics.push(Diagnostic::warning(
+                    source,
+                    &format!("The attachment {} is not found in the mirror.", url),
+                ));
+                return None;
+            }
+        };
+        if let Err(err) = self.copy_asset(file, &target) {
+            self.diagnostics.push(Diagnostic::error(source, &err.reason()));
+            return None;
+        }
+        return Some(self.asset_url(&target));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0"
+    xmlns:excerpt="http://wordpress.org/export/1.2/excerpt/"
+    xmlns:content="http://purl.org/rss/1.0/modules/content/"
+    xmlns:wp="http://wordpress.org/export/1.2/">
+<channel>
+    <title>Blog</title>
+    <wp:category><wp:cat_name><![CDATA[News]]></wp:cat_name></wp:category>
+    <item>
+        <title>Hello</title>
+        <content:encoded><![CDATA[<p>Hello <b>world</b></p>]]></content:encoded>
+        <wp:post_id>1</wp:post_id>
+        <wp:post_date>2018-01-02 10:00:00</wp:post_date>
+        <wp:post_date_gmt>2018-01-02 02:00:00</wp:post_date_gmt>
+        <wp:post_name>hello</wp:post_name>
+        <wp:status>publish</wp:status>
+        <wp:post_type>post</wp:post_type>
+        <category domain="category" nicename="news"><![CDATA[News]]></category>
+        <category domain="post_tag" nicename="rust"><![CDATA[Rust]]></category>
+        <wp:postmeta>
+            <wp:meta_key>_thumbnail_id</wp:meta_key>
+            <wp:meta_value><![CDATA[3]]></wp:meta_value>
+        </wp:postmeta>
+    </item>
+    <item>
+        <title>About</title>
+        <wp:post_id>2</wp:post_id>
+        <wp:post_date>2018-03-04 05:06:07</wp:post_date>
+        <wp:post_date_gmt>0000-00-00 00:00:00</wp:post_date_gmt>
+        <wp:status>draft</wp:status>
+        <wp:post_type>page</wp:post_type>
+    </item>
+</channel>
+</rss>
+"#;
+
+    #[test]
+    fn parse_items_reads_the_status_type_and_terms() {
+        let items = parse_items(EXPORT).unwrap();
+        assert_eq!(items.len(), 2);
+        let post = &items[0];
+        assert_eq!(post.title, "Hello");
+        assert_eq!(post.content, "<p>Hello <b>world</b></p>");
+        assert_eq!(post.status, "publish");
+        assert_eq!(post.post_type, "post");
+        assert_eq!(post.categories, vec!["News"]);
+        assert_eq!(post.tags, vec!["Rust"]);
+        assert_eq!(post.meta.get("_thumbnail_id").map(|x| x.as_str()), Some("3"));
+        assert_eq!(post.date(), Some(Utc.ymd(2018, 1, 2).and_hms(2, 0, 0)));
+        let page = &items[1];
+        assert_eq!(page.status, "draft");
+        assert_eq!(page.post_type, "page");
+        assert!(page.categories.is_empty() && page.tags.is_empty());
+        // The GMT date of a draft is empty, the local date is used.
+        assert_eq!(page.date(), Some(Utc.ymd(2018, 3, 4).and_hms(5, 6, 7)));
+    }
+
+    #[test]
+    fn parse_items_reports_the_position_of_broken_xml() {
+        let err = parse_items("<rss><channel><item></channel></rss>").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::FrontMatter);
+        assert!(err.location.and_then(|x| x.line).is_some());
+    }
+
+    #[test]
+    fn html_to_markdown_converts_the_common_tags() {
+        let html = concat!(
+            "<!-- wp:paragraph --><p>Some <strong>bold</strong> and ",
+            "<a title=\"x\" HREF='https://example.com/a'>link</a>.</p><!-- /wp:paragraph -->\n",
+            "<h2>Title</h2><pre><code>if a &lt; b {}</code></pre>",
+            "<ul><li>one</li><li><em>two</em></li></ul>",
+            "<p><img data-src=\"/lazy.png\" src=\"/cat.png\" alt=\"cat\"></p>",
+        );
+        assert_eq!(
+            html_to_markdown(html),
+            concat!(
+                "Some **bold** and [link](https://example.com/a).\n\n",
+                "## Title\n\n",
+                "```\nif a < b {}\n```\n\n",
+                "- one\n- *two*\n\n",
+                "![cat](/cat.png)\n",
+            )
+        );
+    }
+
+    #[test]
+    fn html_to_markdown_keeps_the_code_blocks() {
+        let html = "<blockquote>quote</blockquote><pre>&lt;b&gt;not bold&lt;/b&gt;\n\n\n</pre>";
+        assert_eq!(html_to_markdown(html), "> quote\n\n```\n<b>not bold</b>\n```\n");
+    }
+}